@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use crate::math::{cross, norm, radians, Point3, Ray, Vec3};
+use crate::sampler::next_2d;
 
 pub struct CameraParams {
 	pub look_from: Point3,
@@ -63,7 +64,7 @@ impl Camera {
 	}
 
 	fn calc_cam_coordinate_system(look_from: Point3, look_at: Point3) -> (Vec3, Vec3, Vec3) {
-		let w = norm(&(&look_from - &look_at));
+		let w = norm(&(look_from - look_at));
 		let u = norm(&cross(&Vec3::from(0.0, 1.0, 0.0), &w));
 		let v = cross(&w, &u);
 
@@ -84,7 +85,8 @@ impl DefocusBlurGenerator {
 		let horizontal = focus_distance * cam.viewport_width() * u;
 		let vertical = focus_distance * cam.viewport_height() * v;
 		Self {
-			upper_left_corner: cam.origin() - &horizontal / 2.0 + &vertical / 2.0 - w * focus_distance,
+			upper_left_corner: cam.origin() - &horizontal / 2.0 + &vertical / 2.0
+				- w * focus_distance,
 			vertical,
 			horizontal,
 			lens_radius: aperture / 2.0,
@@ -96,14 +98,14 @@ impl DefocusBlurGenerator {
 impl RayGenerator for DefocusBlurGenerator {
 	fn gen_ray(&self, s: f64, t: f64) -> Ray {
 		let (u, v, _) = self.cam.coord_system();
-		let rd = self.lens_radius * Vec3::random_in_unit_disk();
+		let rd = self.lens_radius * Vec3::concentric_disk(next_2d());
 		let offset = u * rd.x() + v * rd.y();
-		let ray_dir = &self.upper_left_corner + &self.horizontal * s
-			- &self.vertical * t
+		let ray_dir = self.upper_left_corner + self.horizontal * s
+			- self.vertical * t
 			- self.cam.origin()
-			- &offset;
+			- offset;
 
-		Ray::from(&(self.cam.origin() + &offset), &ray_dir)
+		Ray::from(&(self.cam.origin() + offset), &ray_dir)
 	}
 }
 
@@ -131,12 +133,12 @@ impl PlainGenerator {
 impl RayGenerator for PlainGenerator {
 	fn gen_ray(&self, s: f64, t: f64) -> Ray {
 		let ray_dir =
-			&self.upper_left_corner + &self.horizontal * s - &self.vertical * t - self.cam.origin();
+			self.upper_left_corner + self.horizontal * s - self.vertical * t - self.cam.origin();
 
 		Ray::from(self.cam.origin(), &ray_dir)
 	}
 }
 
-pub trait RayGenerator {
+pub trait RayGenerator: Send + Sync {
 	fn gen_ray(&self, s: f64, t: f64) -> Ray;
 }
@@ -0,0 +1,330 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+use crate::filter::Film;
+use crate::utils::Welford;
+use crate::{Color, Settings};
+
+// identifies the file and the version of its layout
+const MAGIC: &[u8; 8] = b"RTCKPT01";
+// sum and weight, luminance statistics and the active flag
+const PIXEL_BYTES: usize = 4 * 8 + (4 + 2 * 8) + 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+	Io {
+		path: String,
+		source: std::io::Error,
+	},
+	Parse {
+		path: String,
+		message: String,
+	},
+}
+
+impl fmt::Display for CheckpointError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CheckpointError::Io { path, source } => write!(f, "{}: {}", path, source),
+			CheckpointError::Parse { path, message } => write!(f, "{}: {}", path, message),
+		}
+	}
+}
+
+impl Error for CheckpointError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			CheckpointError::Io { source, .. } => Some(source),
+			CheckpointError::Parse { .. } => None,
+		}
+	}
+}
+
+/// state of a progressive render after a number of passes, continuing it gives the image
+/// of an uninterrupted render; the random numbers of every sample are derived from the seed
+/// and the pixel and sample index, so the settings and the pass count restore them
+pub struct Checkpoint {
+	/// the scene and the settings that change the image, a render only resumes with the
+	/// same ones
+	pub(crate) settings: String,
+	width: i32,
+	height: i32,
+	pub(crate) passes: i32,
+	pub(crate) film: Film,
+	/// running luminance statistics of every pixel
+	pub(crate) pixels: Vec<Welford>,
+	/// pixels that still take samples, adaptive antialiasing retires converged ones
+	pub(crate) active: Vec<bool>,
+}
+
+impl Checkpoint {
+	/// the state before the first pass of a render of the scene identified by `scene`
+	/// with `settings`
+	pub fn new(settings: &Settings, scene: &str) -> Self {
+		let size = (settings.width() * settings.height()) as usize;
+		Self {
+			settings: fingerprint(settings, scene),
+			width: settings.width(),
+			height: settings.height(),
+			passes: 0,
+			film: Film::new(settings.width(), 0..settings.height()),
+			pixels: vec![Welford::new(); size],
+			active: vec![true; size],
+		}
+	}
+
+	pub fn passes(&self) -> i32 {
+		self.passes
+	}
+
+	/// whether the checkpoint was made by a render of the same scene with the same image
+	/// defining settings
+	pub fn matches(&self, settings: &Settings, scene: &str) -> bool {
+		self.settings == fingerprint(settings, scene)
+	}
+
+	pub fn load(path: &str) -> Result<Checkpoint, CheckpointError> {
+		let bytes = fs::read(path).map_err(|source| CheckpointError::Io {
+			path: path.to_string(),
+			source,
+		})?;
+		Checkpoint::parse(&bytes).map_err(|message| CheckpointError::Parse {
+			path: path.to_string(),
+			message,
+		})
+	}
+
+	/// writes the checkpoint next to `path` first and then moves it over, so a render
+	/// killed while saving keeps the previous checkpoint
+	pub fn save(&self, path: &str) -> Result<(), CheckpointError> {
+		let partial = format!("{}.partial", path);
+		fs::write(&partial, self.to_bytes())
+			.and_then(|_| fs::rename(&partial, path))
+			.map_err(|source| CheckpointError::Io {
+				path: path.to_string(),
+				source,
+			})
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = MAGIC.to_vec();
+		bytes.extend((self.settings.len() as u32).to_le_bytes());
+		bytes.extend(self.settings.as_bytes());
+		for value in [self.width, self.height, self.passes].iter() {
+			bytes.extend((*value as u32).to_le_bytes());
+		}
+		let film = self.film.sums().iter().zip(self.film.weights());
+		for ((sum, weight), (pixel, active)) in film.zip(self.pixels.iter().zip(&self.active)) {
+			for value in [sum.x(), sum.y(), sum.z(), *weight].iter() {
+				bytes.extend(value.to_le_bytes());
+			}
+			bytes.extend(pixel.count().to_le_bytes());
+			bytes.extend(pixel.mean().to_le_bytes());
+			bytes.extend(pixel.m2().to_le_bytes());
+			bytes.push(*active as u8);
+		}
+		bytes
+	}
+
+	fn parse(bytes: &[u8]) -> Result<Checkpoint, String> {
+		let mut reader = ByteReader { bytes, pos: 0 };
+		if reader.take(MAGIC.len())? != MAGIC {
+			return Err("not a checkpoint of this version".to_string());
+		}
+		let length = reader.u32()? as usize;
+		let settings = String::from_utf8(reader.take(length)?.to_vec())
+			.map_err(|_| "settings are not valid UTF-8".to_string())?;
+		let (width, height) = (reader.u32()?, reader.u32()?);
+		let passes = reader.u32()? as i32;
+		if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+			return Err(format!("invalid image size {}x{}", width, height));
+		}
+		let size = (width as usize)
+			.checked_mul(height as usize)
+			.filter(|size| size.checked_mul(PIXEL_BYTES) == Some(bytes.len() - reader.pos))
+			.ok_or_else(|| format!("pixels do not fit a {}x{} image", width, height))?;
+		let (width, height) = (width as i32, height as i32);
+
+		let mut sums = Vec::with_capacity(size);
+		let mut weights = Vec::with_capacity(size);
+		let mut pixels = Vec::with_capacity(size);
+		let mut active = Vec::with_capacity(size);
+		for _ in 0..size {
+			sums.push(Color::from(reader.f64()?, reader.f64()?, reader.f64()?));
+			weights.push(reader.f64()?);
+			pixels.push(Welford::from(reader.u32()?, reader.f64()?, reader.f64()?));
+			active.push(reader.take(1)?[0] != 0);
+		}
+
+		Ok(Checkpoint {
+			settings,
+			width,
+			height,
+			passes,
+			film: Film::from(width, 0..height, sums, weights),
+			pixels,
+			active,
+		})
+	}
+}
+
+/// the scene and the settings that decide the image, the thread count, the layers and the
+/// display settings may change between a render and its resumption
+fn fingerprint(settings: &Settings, scene: &str) -> String {
+	let r = &settings.rendering;
+	format!(
+		"scene: {:?} {:?} {:?} max_depth: {} {:?} {:?} {:?} {:?} {:?} {:?} {:?} seed: {}",
+		scene,
+		settings.image,
+		settings.camera,
+		r.max_depth,
+		r.russian_roulette,
+		r.antialiasing,
+		r.filter,
+		r.sampling,
+		r.blur,
+		r.acceleration,
+		r.background,
+		r.seed,
+	)
+}
+
+struct ByteReader<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+	fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+		let end = self.pos + count;
+		if end > self.bytes.len() {
+			return Err("checkpoint is truncated".to_string());
+		}
+		let taken = &self.bytes[self.pos..end];
+		self.pos = end;
+		Ok(taken)
+	}
+
+	fn u32(&mut self) -> Result<u32, String> {
+		let mut buffer = [0; 4];
+		buffer.copy_from_slice(self.take(4)?);
+		Ok(u32::from_le_bytes(buffer))
+	}
+
+	fn f64(&mut self) -> Result<f64, String> {
+		let mut buffer = [0; 8];
+		buffer.copy_from_slice(self.take(8)?);
+		Ok(f64::from_le_bytes(buffer))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::settings::{ImageSettings, RenderSettings};
+
+	fn settings(seed: u64) -> Settings {
+		Settings {
+			image: ImageSettings {
+				width: 3,
+				height: 2,
+			},
+			rendering: RenderSettings {
+				seed,
+				..Default::default()
+			},
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let mut checkpoint = Checkpoint::new(&settings(5), "scene");
+		checkpoint.passes = 7;
+		checkpoint.film.splat(
+			&crate::Filter::TENT { radius: 1.0 },
+			(1.3, 0.6),
+			Color::from(0.25, 2.0, 1e-300),
+		);
+		checkpoint.pixels[4].add(0.1);
+		checkpoint.pixels[4].add(0.7);
+		checkpoint.active[2] = false;
+
+		let path = std::env::temp_dir().join(format!(
+			"raytracing_weekend_round_trip_{}.checkpoint",
+			std::process::id()
+		));
+		let path = path.to_str().unwrap();
+		checkpoint.save(path).unwrap();
+		let loaded = Checkpoint::load(path).unwrap();
+		std::fs::remove_file(path).unwrap();
+
+		assert_eq!(loaded.passes(), 7);
+		assert!(loaded.matches(&settings(5), "scene"));
+		assert!(!loaded.matches(&settings(6), "scene"));
+		assert!(!loaded.matches(&settings(5), "other scene"));
+		assert_eq!(loaded.film.sums(), checkpoint.film.sums());
+		assert_eq!(loaded.film.weights(), checkpoint.film.weights());
+		assert_eq!(loaded.pixels[4].count(), 2);
+		assert_eq!(loaded.pixels[4].m2(), checkpoint.pixels[4].m2());
+		assert_eq!(loaded.active, checkpoint.active);
+	}
+
+	#[test]
+	fn test_settings_that_keep_the_image_may_change() {
+		let checkpoint = Checkpoint::new(&settings(0), "scene");
+		let mut other = settings(0);
+		other.rendering.threads = 3;
+		other.display.exposure = 2.0;
+		assert!(checkpoint.matches(&other, "scene"));
+		other.rendering.max_depth = 3;
+		assert!(!checkpoint.matches(&other, "scene"));
+	}
+
+	#[test]
+	fn test_parse_errors() {
+		let bytes = Checkpoint::new(&settings(0), "scene").to_bytes();
+		assert!(Checkpoint::parse(&bytes).is_ok());
+		assert_eq!(
+			Checkpoint::parse(&bytes[..bytes.len() - 1]).err().unwrap(),
+			"pixels do not fit a 3x2 image"
+		);
+		assert_eq!(
+			Checkpoint::parse(&bytes[..10]).err().unwrap(),
+			"checkpoint is truncated"
+		);
+		let header = |width: u32, height: u32| {
+			let mut bytes = MAGIC.to_vec();
+			bytes.extend(0u32.to_le_bytes());
+			for value in [width, height, 0].iter() {
+				bytes.extend(value.to_le_bytes());
+			}
+			bytes
+		};
+		assert_eq!(
+			Checkpoint::parse(&header(u32::MAX, u32::MAX))
+				.err()
+				.unwrap(),
+			"invalid image size 4294967295x4294967295"
+		);
+		assert_eq!(
+			Checkpoint::parse(&header(0, 2)).err().unwrap(),
+			"invalid image size 0x2"
+		);
+		assert_eq!(
+			Checkpoint::parse(&header(i32::MAX as u32, i32::MAX as u32))
+				.err()
+				.unwrap(),
+			"pixels do not fit a 2147483647x2147483647 image"
+		);
+		assert_eq!(
+			Checkpoint::parse(b"P6 not a checkpoint").err().unwrap(),
+			"not a checkpoint of this version"
+		);
+		assert!(matches!(
+			Checkpoint::load("./does/not/exist.checkpoint"),
+			Err(CheckpointError::Io { .. })
+		));
+	}
+}
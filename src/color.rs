@@ -16,4 +16,46 @@ impl Color {
 
 		Color::from(1.0, 1.0, 1.0) * (1.0 - t) + Color::from(0.5, 0.7, 1.0) * t
 	}
+
+	pub fn max_channel(&self) -> f64 {
+		self.x().max(self.y()).max(self.z())
+	}
+
+	pub fn luminance(&self) -> f64 {
+		0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+	}
+}
+
+/// sRGB transfer function from linear values in `0..=1` to encoded values
+pub fn srgb_encode(value: f64) -> f64 {
+	if value <= 0.0031308 {
+		12.92 * value
+	} else {
+		1.055 * value.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+/// inverse of `srgb_encode`
+pub fn srgb_decode(value: f64) -> f64 {
+	if value <= 0.04045 {
+		value / 12.92
+	} else {
+		((value + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_srgb_round_trip() {
+		assert_eq!(srgb_encode(0.0), 0.0);
+		assert!((srgb_encode(1.0) - 1.0).abs() < 1e-12);
+		assert!((srgb_encode(0.5) - 0.735357).abs() < 1e-6);
+		for i in 0..=100 {
+			let v = i as f64 / 100.0;
+			assert!((srgb_decode(srgb_encode(v)) - v).abs() < 1e-12);
+		}
+	}
 }
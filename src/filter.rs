@@ -0,0 +1,294 @@
+use std::f64::consts::PI;
+use std::ops::Range;
+
+use crate::settings::Filter;
+use crate::Color;
+
+impl Filter {
+	/// the filter called `name` in scene files and on the command line with its default radius,
+	/// one of box, tent, gaussian, mitchell, lanczos or blackman_harris
+	pub fn from_name(name: &str) -> Option<Filter> {
+		match name {
+			"box" => Some(Filter::BOX { radius: 0.5 }),
+			"tent" => Some(Filter::TENT { radius: 1.0 }),
+			"gaussian" => Some(Filter::GAUSSIAN {
+				radius: 1.5,
+				alpha: 2.0,
+			}),
+			"mitchell" => Some(Filter::MITCHELL {
+				radius: 2.0,
+				b: 1.0 / 3.0,
+				c: 1.0 / 3.0,
+			}),
+			"lanczos" => Some(Filter::LANCZOS { radius: 3.0 }),
+			"blackman_harris" => Some(Filter::BLACKMAN_HARRIS { radius: 2.0 }),
+			_ => None,
+		}
+	}
+
+	/// the same filter reaching `radius` pixels
+	pub fn with_radius(self, radius: f64) -> Filter {
+		match self {
+			Filter::BOX { .. } => Filter::BOX { radius },
+			Filter::TENT { .. } => Filter::TENT { radius },
+			Filter::GAUSSIAN { alpha, .. } => Filter::GAUSSIAN { radius, alpha },
+			Filter::MITCHELL { b, c, .. } => Filter::MITCHELL { radius, b, c },
+			Filter::LANCZOS { .. } => Filter::LANCZOS { radius },
+			Filter::BLACKMAN_HARRIS { .. } => Filter::BLACKMAN_HARRIS { radius },
+		}
+	}
+
+	pub fn radius(&self) -> f64 {
+		match *self {
+			Filter::BOX { radius }
+			| Filter::TENT { radius }
+			| Filter::GAUSSIAN { radius, .. }
+			| Filter::MITCHELL { radius, .. }
+			| Filter::LANCZOS { radius }
+			| Filter::BLACKMAN_HARRIS { radius } => radius,
+		}
+	}
+
+	/// number of rows above and below its own that the samples of a pixel reach
+	pub fn margin(&self) -> i32 {
+		(self.radius() - 0.5).ceil().max(0.0) as i32
+	}
+
+	/// weight of a sample at the offset `(x, y)` in pixels from a pixel center, offsets
+	/// of exactly the radius still count so a box of radius 0.5 tiles the image
+	pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+		self.evaluate_1d(x) * self.evaluate_1d(y)
+	}
+
+	fn evaluate_1d(&self, x: f64) -> f64 {
+		let x = x.abs();
+		let radius = self.radius();
+		if x > radius {
+			return 0.0;
+		}
+		match *self {
+			Filter::BOX { .. } => 1.0,
+			Filter::TENT { .. } => radius - x,
+			Filter::GAUSSIAN { alpha, .. } => {
+				((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+			}
+			Filter::MITCHELL { b, c, .. } => mitchell(2.0 * x / radius, b, c),
+			Filter::LANCZOS { .. } => sinc(x) * sinc(x / radius),
+			Filter::BLACKMAN_HARRIS { .. } => {
+				let (a0, a1, a2, a3) = (0.35875, 0.48829, 0.14128, 0.01168);
+				let u = 2.0 * PI * (x + radius) / (2.0 * radius);
+				a0 - a1 * u.cos() + a2 * (2.0 * u).cos() - a3 * (3.0 * u).cos()
+			}
+		}
+	}
+}
+
+fn sinc(x: f64) -> f64 {
+	if x < 1e-5 {
+		return 1.0;
+	}
+	(PI * x).sin() / (PI * x)
+}
+
+// the cubic on `0..2` from Mitchell and Netravali's paper
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+	if x > 1.0 {
+		((-b - 6.0 * c) * x * x * x
+			+ (6.0 * b + 30.0 * c) * x * x
+			+ (-12.0 * b - 48.0 * c) * x
+			+ (8.0 * b + 24.0 * c))
+			/ 6.0
+	} else {
+		((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+			+ (-18.0 + 12.0 * b + 6.0 * c) * x * x
+			+ (6.0 - 2.0 * b))
+			/ 6.0
+	}
+}
+
+/// filter weighted sums of the samples landing on a band of image rows
+pub struct Film {
+	width: i32,
+	rows: Range<i32>,
+	sums: Vec<Color>,
+	weights: Vec<f64>,
+}
+
+impl Film {
+	pub fn new(width: i32, rows: Range<i32>) -> Self {
+		let size = (width * rows.len() as i32) as usize;
+		Self {
+			width,
+			rows,
+			sums: vec![Color::black(); size],
+			weights: vec![0.0; size],
+		}
+	}
+
+	/// a film of the rows `rows` holding the given sums and weights
+	pub fn from(width: i32, rows: Range<i32>, sums: Vec<Color>, weights: Vec<f64>) -> Self {
+		assert_eq!(sums.len(), (width * rows.len() as i32) as usize);
+		assert_eq!(sums.len(), weights.len());
+		Self {
+			width,
+			rows,
+			sums,
+			weights,
+		}
+	}
+
+	pub fn sums(&self) -> &[Color] {
+		&self.sums
+	}
+
+	pub fn weights(&self) -> &[f64] {
+		&self.weights
+	}
+
+	/// adds a sample at the film position `(x, y)` in pixels to every pixel of the band
+	/// whose center lies within the filter radius
+	pub fn splat(&mut self, filter: &Filter, (x, y): (f64, f64), color: Color) {
+		let radius = filter.radius();
+		// pixel `p` is covered when `-radius < p + 0.5 - x <= radius`, so a sample on the
+		// border of two box filtered pixels lands in one of them
+		let first = |c: f64| (c - radius - 0.5).floor() as i32 + 1;
+		let last = |c: f64| (c + radius - 0.5).floor() as i32;
+		for py in first(y).max(self.rows.start)..=last(y).min(self.rows.end - 1) {
+			for px in first(x).max(0)..=last(x).min(self.width - 1) {
+				let weight = filter.evaluate(px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+				if weight == 0.0 {
+					continue;
+				}
+				let index = ((py - self.rows.start) * self.width + px) as usize;
+				self.sums[index] += color * weight;
+				self.weights[index] += weight;
+			}
+		}
+	}
+
+	/// accumulates a film of the same width covering some of the rows of this one
+	pub fn add(&mut self, other: &Film) {
+		let offset = ((other.rows.start - self.rows.start) * self.width) as usize;
+		for (i, (sum, weight)) in other.sums.iter().zip(&other.weights).enumerate() {
+			self.sums[offset + i] += *sum;
+			self.weights[offset + i] += weight;
+		}
+	}
+
+	/// the weighted average of every pixel, black where no weight arrived
+	pub fn colors(&self) -> Vec<Color> {
+		self.sums
+			.iter()
+			.zip(&self.weights)
+			.map(|(&sum, &weight)| {
+				if weight > 0.0 {
+					sum / weight
+				} else {
+					Color::black()
+				}
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const FILTERS: [Filter; 6] = [
+		Filter::BOX { radius: 0.5 },
+		Filter::TENT { radius: 1.0 },
+		Filter::GAUSSIAN {
+			radius: 1.5,
+			alpha: 2.0,
+		},
+		Filter::MITCHELL {
+			radius: 2.0,
+			b: 1.0 / 3.0,
+			c: 1.0 / 3.0,
+		},
+		Filter::LANCZOS { radius: 3.0 },
+		Filter::BLACKMAN_HARRIS { radius: 2.0 },
+	];
+
+	#[test]
+	fn test_filters_peak_at_the_center_and_fade_out_at_the_radius() {
+		for filter in FILTERS.iter() {
+			let radius = filter.radius();
+			let center = filter.evaluate(0.0, 0.0);
+			assert!(center > 0.0, "{:?}", filter);
+			assert_eq!(filter.evaluate(radius * 1.001, 0.0), 0.0, "{:?}", filter);
+			assert_eq!(filter.evaluate(0.0, -radius * 1.001), 0.0, "{:?}", filter);
+			for i in 1..20 {
+				let x = radius * i as f64 / 20.0;
+				assert!(filter.evaluate(x, 0.0) <= center, "{:?} at {}", filter, x);
+				assert_eq!(filter.evaluate(x, 0.0), filter.evaluate(-x, 0.0));
+			}
+			if !matches!(filter, Filter::BOX { .. }) {
+				assert!(filter.evaluate(radius * 0.999, 0.0).abs() < 0.05 * center);
+			}
+		}
+		// the cubic and the sinc have negative lobes
+		assert!(FILTERS[3].evaluate(1.5, 0.0) < 0.0);
+		assert!(FILTERS[4].evaluate(1.5, 0.0) < 0.0);
+	}
+
+	#[test]
+	fn test_from_name() {
+		for (name, filter) in [
+			"box",
+			"tent",
+			"gaussian",
+			"mitchell",
+			"lanczos",
+			"blackman_harris",
+		]
+		.iter()
+		.zip(FILTERS.iter())
+		{
+			assert_eq!(Filter::from_name(name), Some(*filter));
+		}
+		assert_eq!(Filter::from_name("sinc"), None);
+		assert_eq!(
+			Filter::from_name("gaussian").unwrap().with_radius(3.0),
+			Filter::GAUSSIAN {
+				radius: 3.0,
+				alpha: 2.0
+			}
+		);
+	}
+
+	#[test]
+	fn test_margin() {
+		assert_eq!(Filter::BOX { radius: 0.5 }.margin(), 0);
+		assert_eq!(Filter::TENT { radius: 1.0 }.margin(), 1);
+		assert_eq!(Filter::LANCZOS { radius: 3.0 }.margin(), 3);
+	}
+
+	#[test]
+	fn test_box_keeps_samples_in_their_pixel() {
+		let filter = Filter::BOX { radius: 0.5 };
+		let mut film = Film::new(3, 0..2);
+		film.splat(&filter, (1.0, 0.0), Color::from(1.0, 0.0, 0.0));
+		film.splat(&filter, (1.9, 0.9), Color::from(0.0, 1.0, 0.0));
+		let colors = film.colors();
+		assert_eq!(colors[1], Color::from(0.5, 0.5, 0.0));
+		assert_eq!(colors[0], Color::black());
+		assert_eq!(colors[4], Color::black());
+	}
+
+	#[test]
+	fn test_splat_reaches_neighbours_within_the_band() {
+		let filter = Filter::TENT { radius: 1.0 };
+		let mut band = Film::new(3, 1..3);
+		band.splat(&filter, (1.5, 1.9), Color::from(1.0, 1.0, 1.0));
+		let touched: Vec<usize> = (0..6).filter(|&i| band.weights[i] > 0.0).collect();
+		assert_eq!(touched, vec![1, 4]);
+
+		let mut film = Film::new(3, 0..4);
+		film.add(&band);
+		assert_eq!(film.weights[3 + 1], band.weights[1]);
+		assert_eq!(film.colors()[3 + 1], Color::from(1.0, 1.0, 1.0));
+		assert_eq!(film.colors()[0], Color::black());
+	}
+}
@@ -0,0 +1,331 @@
+//! Golden image regression tests.
+//!
+//! Small canonical scenes are rendered with fixed seeds and compared against the
+//! 16 bit references in `assets/golden`. A failing comparison leaves the rendered
+//! image and an amplified difference image in `target/golden`. Run the tests with
+//! `UPDATE_GOLDEN=1` to rewrite the references after an intended change of output.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::color::srgb_decode;
+use crate::loader::{load_ppm, parse_scene};
+use crate::{
+	Antialiasing, Background, CameraSettings, Color, DefocusBlur, ImageSettings, MagicNumber,
+	PPMWriter, Point3, RenderSettings, Renderer, RenderingResult, RussianRoulette, Sampling, Scene,
+	Settings, WriteResult,
+};
+
+const MAX_COLOR: u32 = 65535;
+// channel difference in encoded values above which a pixel counts as changed
+const PIXEL_TOLERANCE: f64 = 0.05;
+// share of pixels allowed to exceed the tolerance, covers paths diverging on rounding
+const MAX_CHANGED_PIXELS: f64 = 0.005;
+const MIN_PSNR: f64 = 40.0;
+const DIFF_SCALE: f64 = 8.0;
+
+#[derive(Debug)]
+struct Comparison {
+	rmse: f64,
+	psnr: f64,
+	max_error: f64,
+	changed_pixels: f64,
+}
+
+impl Comparison {
+	fn from(expected: &[[f64; 3]], actual: &[[f64; 3]]) -> Self {
+		assert_eq!(expected.len(), actual.len());
+		let mut squared = 0.0;
+		let mut max_error: f64 = 0.0;
+		let mut changed = 0;
+		for (e, a) in expected.iter().zip(actual.iter()) {
+			let mut pixel_error: f64 = 0.0;
+			for c in 0..3 {
+				let d = (e[c] - a[c]).abs();
+				squared += d * d;
+				pixel_error = pixel_error.max(d);
+			}
+			max_error = max_error.max(pixel_error);
+			if pixel_error > PIXEL_TOLERANCE {
+				changed += 1;
+			}
+		}
+
+		let rmse = (squared / (3 * expected.len()) as f64).sqrt();
+		Self {
+			rmse,
+			// the peak signal is 1 for encoded values
+			psnr: -20.0 * rmse.log10(),
+			max_error,
+			changed_pixels: changed as f64 / expected.len() as f64,
+		}
+	}
+
+	fn passes(&self) -> bool {
+		self.psnr >= MIN_PSNR && self.changed_pixels <= MAX_CHANGED_PIXELS
+	}
+}
+
+fn golden_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/golden")
+}
+
+fn failure_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden")
+}
+
+/// tone mapped, sRGB encoded channel values in `0..=1` as stored in the references
+fn encode(result: &RenderingResult) -> Vec<[f64; 3]> {
+	let max = MAX_COLOR as f64;
+	result
+		.quantize(MAX_COLOR as i32)
+		.iter()
+		.map(|p| [p.r() as f64 / max, p.g() as f64 / max, p.b() as f64 / max])
+		.collect()
+}
+
+fn write(path: &Path, width: i32, height: i32, colors: Vec<Color>) {
+	let result = RenderingResult {
+		width,
+		height,
+		colors,
+		layers: vec![],
+		display: Default::default(),
+		metadata: vec![],
+	};
+	PPMWriter::from(&path.to_string_lossy(), MagicNumber::P6, MAX_COLOR as i32)
+		.write(result)
+		.expect("Failed writing golden image");
+}
+
+fn check(name: &str, scene: Scene, settings: Settings) {
+	let result = Renderer::from(scene, settings).render();
+	let reference = golden_dir().join(format!("{}.ppm", name));
+
+	if std::env::var_os("UPDATE_GOLDEN").is_some() {
+		fs::create_dir_all(golden_dir()).unwrap();
+		PPMWriter::from(
+			&reference.to_string_lossy(),
+			MagicNumber::P6,
+			MAX_COLOR as i32,
+		)
+		.write(result)
+		.expect("Failed writing golden image");
+		return;
+	}
+
+	if let Err(message) = verify(name, &result, &reference, &failure_dir()) {
+		panic!("{}", message);
+	}
+}
+
+/// compares the result with the reference and writes the rendered and the
+/// difference image to `failures` if they differ too much
+fn verify(
+	name: &str,
+	result: &RenderingResult,
+	reference: &Path,
+	failures: &Path,
+) -> Result<(), String> {
+	let expected = load_ppm(&reference.to_string_lossy())
+		.map_err(|e| format!("{}, run the tests with UPDATE_GOLDEN=1 to create it", e))?;
+	if (expected.width, expected.height) != (result.width as usize, result.height as usize) {
+		return Err(format!("{}: dimensions differ from the reference", name));
+	}
+	let max = expected.max_color as f64;
+	let expected: Vec<[f64; 3]> = expected
+		.data
+		.iter()
+		.map(|[r, g, b]| [*r as f64 / max, *g as f64 / max, *b as f64 / max])
+		.collect();
+	let actual = encode(result);
+	let comparison = Comparison::from(&expected, &actual);
+	if comparison.passes() {
+		return Ok(());
+	}
+
+	// linear values that encode to the rendered and the amplified difference images
+	let to_color =
+		|p: &[f64; 3]| Color::from(srgb_decode(p[0]), srgb_decode(p[1]), srgb_decode(p[2]));
+	let diff = expected
+		.iter()
+		.zip(actual.iter())
+		.map(|(e, a)| {
+			let d = |c: usize| ((e[c] - a[c]).abs() * DIFF_SCALE).min(1.0);
+			to_color(&[d(0), d(1), d(2)])
+		})
+		.collect();
+	fs::create_dir_all(failures).map_err(|e| e.to_string())?;
+	let actual_path = failures.join(format!("{}.actual.ppm", name));
+	let diff_path = failures.join(format!("{}.diff.ppm", name));
+	write(
+		&actual_path,
+		result.width,
+		result.height,
+		actual.iter().map(to_color).collect(),
+	);
+	write(&diff_path, result.width, result.height, diff);
+
+	Err(format!(
+		"{} differs from its reference: {:?}, see {} and {}",
+		name,
+		comparison,
+		actual_path.display(),
+		diff_path.display()
+	))
+}
+
+fn settings(width: i32, height: i32, samples_per_pixel: i32) -> Settings {
+	Settings {
+		image: ImageSettings { width, height },
+		rendering: RenderSettings {
+			antialiasing: Antialiasing::MSAA { samples_per_pixel },
+			russian_roulette: RussianRoulette::ON { min_depth: 3 },
+			sampling: Sampling::SOBOL,
+			seed: 1,
+			threads: 1,
+			..Default::default()
+		},
+		..Default::default()
+	}
+}
+
+#[test]
+fn test_comparison_metrics() {
+	let expected = vec![[0.5, 0.5, 0.5]; 4];
+	let same = Comparison::from(&expected, &expected);
+	assert_eq!(same.rmse, 0.0);
+	assert!(same.psnr.is_infinite() && same.passes());
+
+	let mut actual = expected.clone();
+	actual[0] = [0.6, 0.5, 0.5];
+	let changed = Comparison::from(&expected, &actual);
+	assert!((changed.max_error - 0.1).abs() < 1e-12);
+	assert_eq!(changed.changed_pixels, 0.25);
+	assert!((changed.rmse - (0.01f64 / 12.0).sqrt()).abs() < 1e-12);
+	assert!(!changed.passes());
+}
+
+#[test]
+fn test_golden_random_spheres() {
+	check("random_spheres", Scene::random(1), settings(48, 32, 4));
+}
+
+#[test]
+fn test_golden_cornell_box() {
+	let mut settings = settings(32, 32, 16);
+	settings.camera = CameraSettings {
+		look_from: Point3::from(278.0, 278.0, -800.0),
+		look_at: Point3::from(278.0, 278.0, 0.0),
+		field_of_view: 40.0,
+	};
+	settings.rendering.blur = DefocusBlur::OFF;
+	settings.rendering.background = Background::SOLID {
+		color: Color::black(),
+	};
+	check("cornell_box", Scene::cornell_box(), settings);
+}
+
+#[test]
+fn test_golden_textures_and_lights() {
+	let (scene, settings) = parse_scene(
+		r#"
+[image]
+width = 40
+height = 30
+
+[camera]
+look_from = [0, 2, 6]
+look_at = [0, 0.5, 0]
+field_of_view = 40
+
+[render]
+samples_per_pixel = 8
+max_depth = 10
+background = [0.05, 0.05, 0.1]
+seed = 1
+threads = 1
+
+[display]
+tone_mapping = "aces"
+
+[materials.ground]
+type = "lambertian"
+albedo = { type = "checker", scale = 2, even = [0.2, 0.3, 0.1], odd = [0.9, 0.9, 0.9] }
+
+[materials.marble]
+type = "lambertian"
+albedo = { type = "marble", seed = 5, scale = 4, base = [0.9, 0.9, 0.85], vein = [0.2, 0.2, 0.3] }
+
+[materials.gold]
+type = "metal"
+albedo = [0.8, 0.6, 0.2]
+fuzz = 0.2
+
+[materials.glass]
+type = "dielectric"
+refraction_index = 1.5
+
+[materials.light]
+type = "diffuse_light"
+emit = [6, 5, 4]
+
+[[entities]]
+type = "sphere"
+center = [0, -1000, 0]
+radius = 1000
+material = "ground"
+
+[[entities]]
+type = "sphere"
+center = [-1.2, 0.5, 0]
+radius = 0.5
+material = "marble"
+
+[[entities]]
+type = "sphere"
+center = [0, 0.5, 0]
+radius = 0.5
+material = "glass"
+
+[[entities]]
+type = "sphere"
+center = [1.2, 0.5, 0]
+radius = 0.5
+material = "gold"
+
+[[entities]]
+type = "rectangle"
+min = [-1, 2.5, -1]
+max = [1, 2.5, 1]
+material = "light"
+"#,
+		"textures_and_lights",
+		Path::new("."),
+	)
+	.unwrap();
+	check("textures_and_lights", scene, settings);
+}
+
+#[test]
+fn test_failure_writes_diff_image() {
+	let render = |color: Color| {
+		let mut settings = settings(8, 6, 1);
+		settings.rendering.background = Background::SOLID { color };
+		Renderer::from(Scene::new(), settings).render()
+	};
+	let dir =
+		std::env::temp_dir().join(format!("raytracing_weekend_golden_{}", std::process::id()));
+	let reference = dir.join("reference.ppm");
+	fs::create_dir_all(&dir).unwrap();
+	write(&reference, 8, 6, render(Color::from(0.0, 0.0, 1.0)).colors);
+
+	let red = render(Color::from(1.0, 0.0, 0.0));
+	let message = verify("red", &red, &reference, &dir).unwrap_err();
+	assert!(message.starts_with("red differs from its reference"));
+	let diff = load_ppm(&dir.join("red.diff.ppm").to_string_lossy()).unwrap();
+	assert_eq!(diff.data[0], [65535, 0, 65535]);
+
+	let blue = render(Color::from(0.0, 0.0, 1.0));
+	assert!(verify("blue", &blue, &reference, &dir).is_ok());
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,112 +1,407 @@
 #![allow(non_upper_case_globals)]
 use camera::PlainGenerator;
 use camera::RayGenerator;
+pub use checkpoint::{Checkpoint, CheckpointError};
 pub use color::Color;
+pub use loader::{load_scene, parse_scene, ObjError, ObjLoader, PpmError, SceneError};
 pub use math::{Point3, Ray};
-pub use scene::Hit;
+pub use pixel::Pixel;
 pub use scene::Scene;
-pub use scene::{Dielectric, Lambertian, Material, Metal, Sphere};
+pub use scene::{
+	BsdfSample, Dielectric, DiffuseLight, Lambertian, Material, Metal, Sphere, Triangle,
+	TriangleMesh,
+};
+pub use scene::{
+	CheckerTexture, ImageTexture, MarbleTexture, NoiseTexture, Perlin, SolidColor, Texture,
+	UvCheckerTexture, WoodTexture,
+};
+pub use scene::{Entity, Hit};
 pub use settings::{
-	Antialiasing, CameraSettings, DefocusBlur, ImageSettings, RenderSettings, Settings,
+	Acceleration, Antialiasing, Aov, Background, CameraSettings, DefocusBlur, DisplaySettings,
+	Filter, ImageSettings, Progressive, RenderSettings, RussianRoulette, Sampling, Settings,
+	ToneMapping,
 };
 
+pub use sampler::{
+	CmjSampler, HaltonSampler, RandomSampler, Sampler, SobolSampler, StratifiedSampler,
+};
 pub use utils::{aspect_ratio, calc_height};
-pub use writer::{PPMWriter, WriteResult};
+pub use writer::{
+	writer_for_format, writer_for_path, EXRCompression, EXRSampleType, EXRWriter, MagicNumber,
+	PFMWriter, PNGColorType, PNGWriter, PPMWriter, WriteResult,
+};
 
+use crate::camera::Camera;
 use crate::camera::DefocusBlurGenerator;
-use crate::pixel::Pixel;
-use crate::utils::ProgressBar;
-use crate::{camera::Camera, math::rand};
+use crate::color::srgb_decode;
+use crate::filter::Film;
+use crate::math::{power_heuristic, reseed, sample_seed};
+use crate::sampler::{next_1d, next_2d, start_sample};
+use crate::scene::HitRecord;
+use crate::utils::{ProgressBar, Welford};
 use camera::CameraParams;
 
 use crate::math::INFINITY;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::Arc;
 
 mod camera;
+mod checkpoint;
 mod color;
+mod filter;
+#[cfg(test)]
+mod golden;
+mod loader;
 mod math;
 mod pixel;
+mod progressive;
+mod sampler;
 mod scene;
 mod settings;
+mod tonemap;
 mod utils;
 mod writer;
 
+// mean luminance below which the adaptive error is measured absolutely
+const ERROR_FLOOR: f64 = 0.01;
+// rows rendered in parallel before their films are merged into the image
+const BAND_ROWS: usize = 64;
+
 pub struct Renderer {
-	scene: Scene,
+	scene: Arc<Scene>,
 	settings: Settings,
 	ray_generator: Box<dyn RayGenerator>,
+	sampler: Arc<dyn Sampler>,
 }
 
 pub struct RenderingResult {
 	pub width: i32,
 	pub height: i32,
-	pub pixels: Vec<Pixel>,
+	/// linear radiance per pixel stored row by row, values are not clamped to `0..=1`
+	pub colors: Vec<Color>,
+	/// auxiliary layers with the same dimensions as `colors`
+	pub layers: Vec<Layer>,
+	/// tone mapping applied by low dynamic range writers
+	pub display: DisplaySettings,
+	/// key value pairs describing how the image was made, stored by writers that support it
+	pub metadata: Vec<(String, String)>,
+}
+
+pub struct Layer {
+	pub name: String,
+	pub colors: Vec<Color>,
+}
+
+impl RenderingResult {
+	/// tone mapped, sRGB encoded 8 bit pixels
+	pub fn pixels(&self) -> Vec<Pixel> {
+		self.quantize(255)
+	}
+
+	/// the `samples` layer as an image running from blue for the fewest samples to red
+	/// for the most, `None` without the layer
+	pub fn heatmap(&self) -> Option<RenderingResult> {
+		let samples = self.layers.iter().find(|l| l.name == "samples")?;
+		let counts: Vec<f64> = samples.colors.iter().map(|c| c.x()).collect();
+		let low = counts.iter().copied().fold(f64::INFINITY, f64::min);
+		let high = counts.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+		let colors = counts
+			.iter()
+			.map(|&n| {
+				let t = if high > low {
+					(n - low) / (high - low)
+				} else {
+					0.0
+				};
+				heat(t)
+			})
+			.collect();
+
+		Some(RenderingResult {
+			width: self.width,
+			height: self.height,
+			colors,
+			layers: vec![],
+			display: DisplaySettings::default(),
+			metadata: self.metadata.clone(),
+		})
+	}
+
+	/// tone maps and sRGB encodes the framebuffer, then quantizes it to channel values in `0..=max_color`
+	pub fn quantize(&self, max_color: i32) -> Vec<Pixel> {
+		self.colors
+			.iter()
+			.map(|c| Pixel::from_color_with_max(self.display.apply(*c), max_color))
+			.collect()
+	}
+}
+
+/// standard error of the mean luminance relative to the mean, dark pixels are judged
+/// against a floor so noise in black areas can end
+fn relative_error(luminance: &Welford) -> f64 {
+	luminance.standard_error() / luminance.mean().max(ERROR_FLOOR)
+}
+
+/// linear color of `t` in `0..=1` on a blue, cyan, green, yellow, red ramp
+fn heat(t: f64) -> Color {
+	const RAMP: [(f64, f64, f64); 5] = [
+		(0.05, 0.1, 0.6),
+		(0.1, 0.7, 0.9),
+		(0.2, 0.8, 0.2),
+		(0.95, 0.9, 0.1),
+		(0.9, 0.1, 0.1),
+	];
+	let x = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+	let i = (x as usize).min(RAMP.len() - 2);
+	let f = x - i as f64;
+	let (a, b) = (RAMP[i], RAMP[i + 1]);
+	// interpolated in display space, the writers encode the result again
+	let mix = |a: f64, b: f64| srgb_decode(a + (b - a) * f);
+	Color::from(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+impl Default for Renderer {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Renderer {
 	pub fn new() -> Self {
-		let settings: Settings = Default::default();
-		let ray_generator = Renderer::get_ray_generator(&settings);
-		Self {
-			scene: Scene::random(),
-			settings,
-			ray_generator,
-		}
+		let settings = Settings::default();
+		Renderer::from(Scene::random(settings.seed()), settings)
+	}
+
+	pub fn from(mut scene: Scene, settings: Settings) -> Self {
+		scene.build(settings.acceleration());
+		Renderer::from_shared(Arc::new(scene), settings)
 	}
 
-	pub fn from(scene: Scene, settings: Settings) -> Self {
+	/// creates a renderer for a scene that is shared with other renderers,
+	/// the scene has to be built with `Scene::build` beforehand to be accelerated
+	pub fn from_shared(scene: Arc<Scene>, settings: Settings) -> Self {
 		Self {
 			scene,
 			ray_generator: Renderer::get_ray_generator(&settings),
+			sampler: sampler::from_settings(&settings),
 			settings,
 		}
 	}
 
 	pub fn render(&self) -> RenderingResult {
-		let progress = &ProgressBar::from(self.settings.height() * self.settings.width());
-		let pixels: Vec<Pixel> = (0..self.settings.height())
-			.into_iter()
-			.flat_map(|j| {
-				(0..self.settings.width()).into_iter().map(move |i| {
+		let pool = self.thread_pool();
+		let progress = &ProgressBar::from(self.settings.height());
+		let mut film = Film::new(self.settings.width(), 0..self.settings.height());
+		let mut samples =
+			Vec::with_capacity((self.settings.width() * self.settings.height()) as usize);
+		pool.install(|| {
+			self.render_rows(
+				|j| {
+					let row = self.render_row(j);
 					progress.inc();
-					Pixel::from_color(self.pixel_color(i, j))
-				})
+					row
+				},
+				|(row, counts)| {
+					film.add(&row);
+					samples.extend(counts);
+				},
+			)
+		});
+		let layers = self.layers(&pool, &samples);
+
+		self.result(film.colors(), layers)
+	}
+
+	fn thread_pool(&self) -> ThreadPool {
+		ThreadPoolBuilder::new()
+			.num_threads(self.settings.threads())
+			.build()
+			.expect("Failed building render thread pool")
+	}
+
+	/// renders bands of rows in parallel and hands every row to `merge` in order, so
+	/// the sums of a film do not depend on the order in which threads finish
+	fn render_rows<T: Send>(&self, render_row: impl Fn(i32) -> T + Sync, mut merge: impl FnMut(T)) {
+		let rows: Vec<i32> = (0..self.settings.height()).collect();
+		for band in rows.chunks(BAND_ROWS) {
+			let rendered: Vec<T> = band.par_iter().map(|&j| render_row(j)).collect();
+			rendered.into_iter().for_each(&mut merge);
+		}
+	}
+
+	/// the auxiliary layers of the settings, `samples` holds the samples taken per pixel
+	fn layers(&self, pool: &ThreadPool, samples: &[i32]) -> Vec<Layer> {
+		self.settings
+			.aovs()
+			.iter()
+			.map(|aov| Layer {
+				name: format!("{:?}", aov).to_lowercase(),
+				colors: match aov {
+					Aov::SAMPLES => samples
+						.iter()
+						.map(|&n| Color::from(n as f64, n as f64, n as f64))
+						.collect(),
+					_ => pool.install(|| self.render_aov(*aov)),
+				},
 			})
-			.collect();
+			.collect()
+	}
 
+	fn result(&self, colors: Vec<Color>, layers: Vec<Layer>) -> RenderingResult {
 		RenderingResult {
 			width: self.settings.width(),
 			height: self.settings.height(),
-			pixels,
+			colors,
+			layers,
+			display: self.settings.display,
+			metadata: self.metadata(),
 		}
 	}
 
-	fn pixel_color(&self, curr_width: i32, curr_height: i32) -> Color {
-		match self.settings.antialiasing() {
-			Antialiasing::MSAA { samples_per_pixel } => {
-				self.get_average_color(*samples_per_pixel, curr_width, curr_height)
+	fn render_aov(&self, aov: Aov) -> Vec<Color> {
+		(0..self.settings.height())
+			.into_par_iter()
+			.flat_map_iter(|j| (0..self.settings.width()).map(move |i| self.aov_color(aov, i, j)))
+			.collect()
+	}
+
+	fn aov_color(&self, aov: Aov, curr_width: i32, curr_height: i32) -> Color {
+		self.seed_sample(curr_width, curr_height, 0);
+		let s = (curr_width as f64 + 0.5) / ((self.settings.width() as f64) - 1.0);
+		let t = (curr_height as f64 + 0.5) / ((self.settings.height() as f64) - 1.0);
+		let r = self.ray_generator.gen_ray(s, t);
+		let rec = match self.scene.intersect(&r, 0.001, INFINITY) {
+			Some(rec) => rec,
+			None => return Color::black(),
+		};
+
+		match aov {
+			Aov::ALBEDO => {
+				let mat = rec.material();
+				mat.sample(&r, &rec)
+					.map(|sample| sample.weight)
+					.unwrap_or_else(Color::black)
+			}
+			Aov::NORMAL => *rec.normal(),
+			Aov::DEPTH => {
+				let depth = rec.t() * r.direction().len();
+				Color::from(depth, depth, depth)
 			}
-			Antialiasing::NONE => self.get_color(curr_width, curr_height),
+			Aov::SAMPLES => unreachable!("sample counts are recorded by the image pass"),
 		}
 	}
 
-	fn calc_viewport_coordinates(&self, curr_width: i32, curr_height: i32) -> (f64, f64) {
-		let s: f64 = (curr_width as f64 + rand()) / ((self.settings.width() as f64) - 1.0);
-		let t: f64 = (curr_height as f64 + rand()) / ((self.settings.height() as f64) - 1.0);
-		(s, t)
+	fn metadata(&self) -> Vec<(String, String)> {
+		vec![
+			(
+				"Software".to_string(),
+				format!("raytracing_weekend {}", env!("CARGO_PKG_VERSION")),
+			),
+			(
+				"Render settings".to_string(),
+				format!("{:?}", self.settings),
+			),
+		]
+	}
+
+	/// splats the samples of row `curr_height` onto a film of the rows they reach,
+	/// together with the number of samples every pixel took
+	fn render_row(&self, curr_height: i32) -> (Film, Vec<i32>) {
+		let mut film = self.row_film(curr_height);
+		let counts = (0..self.settings.width())
+			.map(|i| self.render_pixel(&mut film, i, curr_height))
+			.collect();
+		(film, counts)
+	}
+
+	/// an empty film of the rows that samples of row `curr_height` reach
+	fn row_film(&self, curr_height: i32) -> Film {
+		let margin = self.settings.filter().margin();
+		let top = (curr_height - margin).max(0);
+		let bottom = (curr_height + margin + 1).min(self.settings.height());
+		Film::new(self.settings.width(), top..bottom)
 	}
 
-	fn get_color(&self, curr_width: i32, curr_height: i32) -> Color {
-		let (s, t) = self.calc_viewport_coordinates(curr_width, curr_height);
+	/// splats the samples of a pixel and returns how many it took
+	fn render_pixel(&self, film: &mut Film, curr_width: i32, curr_height: i32) -> i32 {
+		let samples_per_pixel = match self.settings.antialiasing() {
+			Antialiasing::MSAA { samples_per_pixel } => *samples_per_pixel,
+			Antialiasing::ADAPTIVE {
+				min_samples,
+				max_samples,
+				threshold,
+			} => {
+				return self.render_adaptive_pixel(
+					film,
+					(*min_samples, *max_samples, *threshold),
+					curr_width,
+					curr_height,
+				)
+			}
+			Antialiasing::NONE => 1,
+		};
+		for sample in 0..samples_per_pixel {
+			self.splat_sample(film, curr_width, curr_height, sample);
+		}
+		samples_per_pixel
+	}
+
+	/// samples until the relative error of the mean luminance reaches `threshold`
+	fn render_adaptive_pixel(
+		&self,
+		film: &mut Film,
+		(min_samples, max_samples, threshold): (i32, i32, f64),
+		curr_width: i32,
+		curr_height: i32,
+	) -> i32 {
+		let mut luminance = Welford::new();
+		for sample in 0..max_samples.max(1) {
+			let color = self.splat_sample(film, curr_width, curr_height, sample);
+			luminance.add(color.luminance());
+			if sample + 1 >= min_samples && relative_error(&luminance) <= threshold {
+				break;
+			}
+		}
+
+		luminance.count() as i32
+	}
+
+	/// traces one sample of the pixel, splats it and returns its radiance
+	fn splat_sample(
+		&self,
+		film: &mut Film,
+		curr_width: i32,
+		curr_height: i32,
+		sample: i32,
+	) -> Color {
+		self.seed_sample(curr_width, curr_height, sample);
+		let position = self.film_position(curr_width, curr_height);
+		let (s, t) = self.calc_viewport_coordinates(position);
 		let r = self.ray_generator.gen_ray(s, t);
-		self.trace(&r, self.settings.max_depth())
+		let color = self.trace(&r, self.settings.max_depth());
+		film.splat(self.settings.filter(), position, color);
+		color
 	}
 
-	fn get_average_color(&self, sample_size: i32, curr_width: i32, curr_height: i32) -> Color {
-		let color: Color = (0..sample_size)
-			.map(|_sample| self.get_color(curr_width, curr_height))
-			.sum();
+	/// makes the random numbers of a sample independent of which thread renders it
+	/// and points the sampler at the sample
+	fn seed_sample(&self, curr_width: i32, curr_height: i32, sample: i32) {
+		let pixel = curr_height as u64 * self.settings.width() as u64 + curr_width as u64;
+		reseed(sample_seed(self.settings.seed(), pixel, sample as u64));
+		start_sample(&self.sampler, pixel, sample as u64);
+	}
 
-		color / sample_size as f64
+	/// jittered position of a sample inside the pixel, in pixels
+	fn film_position(&self, curr_width: i32, curr_height: i32) -> (f64, f64) {
+		let (dx, dy) = next_2d();
+		(curr_width as f64 + dx, curr_height as f64 + dy)
+	}
+
+	fn calc_viewport_coordinates(&self, (x, y): (f64, f64)) -> (f64, f64) {
+		let s: f64 = x / ((self.settings.width() as f64) - 1.0);
+		let t: f64 = y / ((self.settings.height() as f64) - 1.0);
+		(s, t)
 	}
 
 	fn get_ray_generator(settings: &Settings) -> Box<dyn RayGenerator> {
@@ -122,9 +417,9 @@ impl Renderer {
 			aspect_ratio: aspect_ratio(settings.width(), settings.height()),
 		});
 
-		match settings.defocus_blur() {
-			&DefocusBlur::OFF => Box::new(PlainGenerator::from(cam)),
-			&DefocusBlur::ON {
+		match *settings.defocus_blur() {
+			DefocusBlur::OFF => Box::new(PlainGenerator::from(cam)),
+			DefocusBlur::ON {
 				aperture,
 				focus_distance,
 			} => Box::new(DefocusBlurGenerator::from(cam, aperture, focus_distance)),
@@ -132,17 +427,92 @@ impl Renderer {
 	}
 
 	fn trace(&self, r: &Ray, depth: i32) -> Color {
-		if depth <= 0 {
-			return Color::black();
-		}
-		if let Some(rec) = self.scene.intersect(&r, 0.001, INFINITY) {
-			if let Some((attenuation, scattered)) = rec.material().scatter(r, rec) {
-				return attenuation * self.trace(&scattered, depth - 1);
+		let mut radiance = Color::black();
+		// share of the light arriving at the current vertex that reaches the camera
+		let mut throughput = Color::from(1.0, 1.0, 1.0);
+		let mut ray = Ray::from(r.origin(), r.direction());
+		// density with which the previous vertex picked the ray, `None` for the camera
+		// ray and specular bounces that light sampling cannot produce
+		let mut bsdf_pdf: Option<f64> = None;
+
+		for bounce in 0..depth {
+			let rec = match self.scene.intersect(&ray, 0.001, INFINITY) {
+				Some(rec) => rec,
+				None => {
+					radiance += throughput * self.background(&ray);
+					break;
+				}
+			};
+
+			let mat = rec.material();
+			let mut emitted = mat.emitted(&rec);
+			if let Some(pdf) = bsdf_pdf {
+				if !emitted.near_zero() {
+					// the previous vertex sampled the lights as well, weight both strategies
+					let light_pdf = self.scene.light_pdf(ray.origin(), ray.direction());
+					emitted = emitted * power_heuristic(pdf, light_pdf);
+				}
+			}
+			radiance += throughput * emitted;
+
+			let sample = match mat.sample(&ray, &rec) {
+				Some(sample) => sample,
+				None => break,
+			};
+			bsdf_pdf = if sample.specular {
+				None
+			} else {
+				// a light sample stands in for a bounce, it needs the same depth left as one
+				if bounce + 1 < depth && !mat.is_specular() {
+					radiance += throughput * self.direct_light(&ray, &rec);
+				}
+				Some(sample.pdf)
+			};
+			throughput *= sample.weight;
+
+			if let RussianRoulette::ON { min_depth } = self.settings.russian_roulette() {
+				if bounce + 1 >= *min_depth {
+					let survival = throughput.max_channel().min(1.0);
+					if next_1d() >= survival {
+						break;
+					}
+					throughput = throughput / survival;
+				}
 			}
+			ray = Ray::from(rec.point(), &sample.direction);
+		}
+
+		radiance
+	}
+
+	/// next event estimation, light reaching the hit point straight from a sampled light
+	fn direct_light(&self, r: &Ray, rec: &HitRecord) -> Color {
+		let direction = match self.scene.sample_light(rec.point()) {
+			Some(direction) => direction,
+			None => return Color::black(),
+		};
+		let light_pdf = self.scene.light_pdf(rec.point(), &direction);
+		let mat = rec.material();
+		let f = mat.eval(r, rec, &direction);
+		if light_pdf <= 0.0 || f.near_zero() {
 			return Color::black();
 		}
 
-		Color::interpolate_by_direction(r)
+		// whatever the shadow ray hits first decides whether the light is occluded
+		let shadow = Ray::from(rec.point(), &direction);
+		let emitted = match self.scene.intersect(&shadow, 0.001, INFINITY) {
+			Some(light) => light.material().emitted(&light),
+			None => return Color::black(),
+		};
+		let weight = power_heuristic(light_pdf, mat.pdf(r, rec, &direction));
+		f * emitted * (weight / light_pdf)
+	}
+
+	fn background(&self, r: &Ray) -> Color {
+		match self.settings.background() {
+			Background::SKY => Color::interpolate_by_direction(r),
+			Background::SOLID { color } => *color,
+		}
 	}
 }
 
@@ -155,7 +525,7 @@ mod test {
 	fn test_render_image_creates_image() {
 		let path = "./assets/test.ppm";
 
-		let scene = Scene::random();
+		let scene = Scene::random(0);
 		let settings = Settings {
 			image: ImageSettings {
 				width: 150,
@@ -180,7 +550,7 @@ mod test {
 		let width = 100;
 		let height = 50;
 
-		let scene = Scene::random();
+		let scene = Scene::random(0);
 		let settings = Settings {
 			rendering: RenderSettings {
 				antialiasing: Antialiasing::NONE,
@@ -192,12 +562,336 @@ mod test {
 
 		let renderer = Renderer::from(scene, settings);
 
-		let (s, t) = renderer.calc_viewport_coordinates(0, 0);
+		let (s, t) = renderer.calc_viewport_coordinates(renderer.film_position(0, 0));
 		assert!(s < 0.1);
 		assert!(t < 0.1);
 
-		let (s, t) = renderer.calc_viewport_coordinates(width - 1, height - 1);
+		let (s, t) =
+			renderer.calc_viewport_coordinates(renderer.film_position(width - 1, height - 1));
 		assert!(s > 0.99);
 		assert!(t > 0.99);
 	}
+
+	#[test]
+	fn test_renderers_share_scene_concurrently() {
+		let mut scene = Scene::random(0);
+		scene.build(&Acceleration::BVH);
+		let scene = Arc::new(scene);
+		let settings = || Settings {
+			image: ImageSettings {
+				width: 30,
+				height: 20,
+			},
+			rendering: RenderSettings {
+				antialiasing: Antialiasing::NONE,
+				threads: 2,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let renderers = [
+			Renderer::from_shared(Arc::clone(&scene), settings()),
+			Renderer::from_shared(Arc::clone(&scene), settings()),
+		];
+
+		std::thread::scope(|s| {
+			for renderer in renderers.iter() {
+				s.spawn(move || assert_eq!(renderer.render().colors.len(), 30 * 20));
+			}
+		});
+	}
+
+	#[test]
+	fn test_seeded_render_is_deterministic() {
+		let render = |seed: u64, threads: usize| {
+			let settings = Settings {
+				image: ImageSettings {
+					width: 24,
+					height: 16,
+				},
+				rendering: RenderSettings {
+					antialiasing: Antialiasing::MSAA {
+						samples_per_pixel: 2,
+					},
+					threads,
+					seed,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			Renderer::from(Scene::random(seed), settings)
+				.render()
+				.colors
+		};
+
+		let reference = render(3, 1);
+		assert_eq!(reference, render(3, 3));
+		assert_ne!(reference, render(4, 1));
+	}
+
+	#[test]
+	fn test_filtered_render() {
+		let render = |scene: Scene, filter: Filter, background: Background, threads: usize| {
+			let settings = Settings {
+				image: ImageSettings {
+					width: 24,
+					height: 16,
+				},
+				rendering: RenderSettings {
+					antialiasing: Antialiasing::MSAA {
+						samples_per_pixel: 2,
+					},
+					filter,
+					background,
+					threads,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			Renderer::from(scene, settings).render().colors
+		};
+
+		// weights are normalized, also where negative lobes leave the image
+		let white = Color::from(1.0, 1.0, 1.0);
+		let mitchell = Filter::MITCHELL {
+			radius: 2.0,
+			b: 1.0 / 3.0,
+			c: 1.0 / 3.0,
+		};
+		let colors = render(
+			Scene::new(),
+			mitchell,
+			Background::SOLID { color: white },
+			1,
+		);
+		assert!(colors.iter().all(|c| (*c - white).len() < 1e-9));
+
+		let gaussian = Filter::GAUSSIAN {
+			radius: 1.5,
+			alpha: 2.0,
+		};
+		let reference = render(Scene::random(0), gaussian, Background::SKY, 1);
+		assert_eq!(
+			reference,
+			render(Scene::random(0), gaussian, Background::SKY, 3)
+		);
+		let boxed = render(
+			Scene::random(0),
+			Filter::BOX { radius: 0.5 },
+			Background::SKY,
+			1,
+		);
+		assert_ne!(reference, boxed);
+	}
+
+	#[test]
+	fn test_trace_adds_emission() {
+		let mut scene = Scene::new();
+		let light = Arc::new(DiffuseLight::from(Color::from(4.0, 2.0, 1.0)));
+		scene.add_entity(Box::new(Sphere::from(
+			Point3::from(0.0, 0.0, -2.0),
+			0.5,
+			light,
+		)));
+		let settings = Settings {
+			rendering: RenderSettings {
+				background: Background::SOLID {
+					color: Color::black(),
+				},
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let renderer = Renderer::from(scene, settings);
+
+		let towards = Ray::from(&Point3::new(), &Point3::from(0.0, 0.0, -1.0));
+		assert_eq!(renderer.trace(&towards, 5), Color::from(4.0, 2.0, 1.0));
+
+		let away = Ray::from(&Point3::new(), &Point3::from(0.0, 0.0, 1.0));
+		assert_eq!(renderer.trace(&away, 5), Color::black());
+	}
+
+	#[test]
+	fn test_direct_light_under_small_light() {
+		// a point on a white floor straight below a small spherical light receives
+		// albedo * emission * radius^2 / height^2 after one bounce
+		let mut scene = Scene::new();
+		let floor = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+		let light = Arc::new(DiffuseLight::from(Color::from(16.0, 16.0, 16.0)));
+		let p = Point3::from;
+		let min = p(-10.0, 0.0, -10.0);
+		scene.add_entity(Box::new(Scene::rectangle(min, p(10.0, 0.0, 10.0), floor)));
+		scene.add_entity(Box::new(Sphere::from(p(0.0, 2.0, 0.0), 0.25, light)));
+		let settings = Settings {
+			rendering: RenderSettings {
+				background: Background::SOLID {
+					color: Color::black(),
+				},
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let renderer = Renderer::from(scene, settings);
+
+		reseed(5);
+		let r = Ray::from(&p(0.0, 1.0, 1.0), &p(0.0, -1.0, -1.0));
+		let samples = 2000;
+		let color: Color = (0..samples).map(|_| renderer.trace(&r, 2)).sum();
+		let expected = 0.5 * 16.0 * 0.25 * 0.25 / 4.0;
+		assert!(((color / samples as f64).x() - expected).abs() < 0.03 * expected);
+	}
+
+	#[test]
+	fn test_russian_roulette_is_unbiased() {
+		// a convex gray sphere under a white sky reflects exactly its albedo, with
+		// roulette from the first bounce on half of the paths end there weighted zero
+		let renderer = |russian_roulette| {
+			let mut scene = Scene::new();
+			let gray = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+			scene.add_entity(Box::new(Sphere::from(Point3::new(), 1.0, gray)));
+			let settings = Settings {
+				rendering: RenderSettings {
+					background: Background::SOLID {
+						color: Color::from(1.0, 1.0, 1.0),
+					},
+					russian_roulette,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			Renderer::from(scene, settings)
+		};
+		let r = Ray::from(&Point3::from(0.0, 0.0, 3.0), &Point3::from(0.0, 0.0, -1.0));
+		let off = renderer(RussianRoulette::OFF);
+		let on = renderer(RussianRoulette::ON { min_depth: 1 });
+
+		reseed(9);
+		assert_eq!(
+			off.trace(&r, 10).round_to(1_000_000),
+			Color::from(0.5, 0.5, 0.5)
+		);
+		let samples: Vec<f64> = (0..4000).map(|_| on.trace(&r, 10).x()).collect();
+		assert!(samples.iter().all(|&s| s == 0.0 || (s - 1.0).abs() < 1e-9));
+		let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+		assert!((mean - 0.5).abs() < 0.03);
+	}
+
+	#[test]
+	fn test_adaptive_sampling() {
+		// the black background converges at once, the sphere lit by a small light does not
+		let mut scene = Scene::new();
+		let gray = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+		let light = Arc::new(DiffuseLight::from(Color::from(8.0, 8.0, 8.0)));
+		scene.add_entity(Box::new(Sphere::from(Point3::new(), 1.0, gray)));
+		scene.add_entity(Box::new(Sphere::from(
+			Point3::from(0.0, 2.0, 3.0),
+			0.5,
+			light,
+		)));
+		let settings = Settings {
+			image: ImageSettings {
+				width: 9,
+				height: 9,
+			},
+			rendering: RenderSettings {
+				antialiasing: Antialiasing::ADAPTIVE {
+					min_samples: 4,
+					max_samples: 64,
+					threshold: 0.01,
+				},
+				blur: DefocusBlur::OFF,
+				aovs: vec![Aov::SAMPLES],
+				background: Background::SOLID {
+					color: Color::black(),
+				},
+				..Default::default()
+			},
+			camera: CameraSettings {
+				look_from: Point3::from(0.0, 0.0, 5.0),
+				field_of_view: 40.0,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let result = Renderer::from(scene, settings).render();
+
+		let samples = &result.layers[0];
+		assert_eq!(samples.name, "samples");
+		assert_eq!(samples.colors[0], Color::from(4.0, 4.0, 4.0));
+		let center = samples.colors[4 * 9 + 4].x();
+		assert!(center > 4.0 && center <= 64.0);
+
+		let heatmap = result.heatmap().unwrap();
+		assert_eq!(heatmap.colors.len(), 81);
+		assert_eq!(heatmap.colors[0], heat(0.0));
+		assert_ne!(heatmap.colors[4 * 9 + 4], heat(0.0));
+
+		let mut result = result;
+		result.layers.clear();
+		assert!(result.heatmap().is_none());
+	}
+
+	#[test]
+	fn test_render_keeps_high_dynamic_range() {
+		let mut scene = Scene::new();
+		let light = Arc::new(DiffuseLight::from(Color::from(4.0, 2.0, 0.5)));
+		scene.add_entity(Box::new(Sphere::from(Point3::new(), 1.0, light)));
+		let settings = Settings {
+			image: ImageSettings {
+				width: 9,
+				height: 9,
+			},
+			rendering: RenderSettings {
+				antialiasing: Antialiasing::NONE,
+				background: Background::SOLID {
+					color: Color::black(),
+				},
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let result = Renderer::from(scene, settings).render();
+
+		let center = result.colors[4 * 9 + 4];
+		assert!(result.layers.is_empty());
+		assert_eq!(center, Color::from(4.0, 2.0, 0.5));
+		let p = result.pixels()[4 * 9 + 4];
+		assert_eq!((p.r(), p.g(), p.b()), (255, 255, 188));
+		assert_eq!(result.quantize(65535).len(), 81);
+	}
+
+	#[test]
+	fn test_render_aovs() {
+		let mut scene = Scene::new();
+		let mat = Arc::new(Lambertian::from(Color::from(0.2, 0.4, 0.8)));
+		scene.add_entity(Box::new(Sphere::from(Point3::new(), 1.0, mat)));
+		let settings = Settings {
+			image: ImageSettings {
+				width: 9,
+				height: 9,
+			},
+			rendering: RenderSettings {
+				antialiasing: Antialiasing::NONE,
+				blur: DefocusBlur::OFF,
+				aovs: vec![Aov::ALBEDO, Aov::NORMAL, Aov::DEPTH],
+				..Default::default()
+			},
+			camera: CameraSettings {
+				look_from: Point3::from(0.0, 0.0, 5.0),
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let result = Renderer::from(scene, settings).render();
+
+		let names: Vec<&str> = result.layers.iter().map(|l| l.name.as_str()).collect();
+		assert_eq!(names, vec!["albedo", "normal", "depth"]);
+		assert!(result.layers.iter().all(|l| l.colors.len() == 81));
+
+		let center = 4 * 9 + 4;
+		assert_eq!(result.layers[0].colors[center], Color::from(0.2, 0.4, 0.8));
+		assert!(result.layers[1].colors[center].z() > 0.95);
+		assert!((result.layers[2].colors[center].x() - 4.0).abs() < 0.05);
+		assert_eq!(result.layers[2].colors[0], Color::black());
+	}
 }
@@ -0,0 +1,7 @@
+pub use obj::{ObjError, ObjLoader};
+pub use ppm::{load_ppm, PpmError};
+pub use scene::{load_scene, parse_scene, SceneError};
+
+pub mod obj;
+pub mod ppm;
+pub mod scene;
@@ -0,0 +1,625 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::math::{cross, norm, Point3, Vec3};
+use crate::scene::{Dielectric, Entity, Lambertian, Material, Metal, TriangleMesh};
+use crate::Color;
+
+#[derive(Debug)]
+pub enum ObjError {
+	Io {
+		path: String,
+		source: std::io::Error,
+	},
+	Parse {
+		path: String,
+		line: usize,
+		message: String,
+	},
+}
+
+impl fmt::Display for ObjError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ObjError::Io { path, source } => write!(f, "{}: {}", path, source),
+			ObjError::Parse {
+				path,
+				line,
+				message,
+			} => write!(f, "{}:{}: {}", path, line, message),
+		}
+	}
+}
+
+impl Error for ObjError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ObjError::Io { source, .. } => Some(source),
+			ObjError::Parse { .. } => None,
+		}
+	}
+}
+
+/// Loads Wavefront OBJ files into triangle meshes, one mesh per group and material.
+/// Materials referenced through `mtllib`/`usemtl` are mapped onto the closest
+/// material of this crate, faces without a material use the default one.
+pub struct ObjLoader {
+	default_material: Arc<dyn Material>,
+}
+
+impl Default for ObjLoader {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl ObjLoader {
+	pub fn new() -> Self {
+		Self {
+			default_material: Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5))),
+		}
+	}
+
+	pub fn from(default_material: Arc<dyn Material>) -> Self {
+		Self { default_material }
+	}
+
+	pub fn load(&self, path: &str) -> Result<Vec<Entity>, ObjError> {
+		let source = read(Path::new(path))?;
+		let base_dir = Path::new(path)
+			.parent()
+			.map(Path::to_path_buf)
+			.unwrap_or_default();
+		self.parse(&source, path, &base_dir)
+	}
+
+	/// parses OBJ source, `name` is used in error messages and material libraries
+	/// are resolved relative to `base_dir`
+	pub fn parse(
+		&self,
+		source: &str,
+		name: &str,
+		base_dir: &Path,
+	) -> Result<Vec<Entity>, ObjError> {
+		let mut parser = ObjParser::from(Arc::clone(&self.default_material));
+
+		for (i, raw) in source.lines().enumerate() {
+			let line = i + 1;
+			let err = |message: String| ObjError::Parse {
+				path: name.to_string(),
+				line,
+				message,
+			};
+			let mut tokens = strip_comment(raw).split_whitespace();
+			let keyword = match tokens.next() {
+				Some(k) => k,
+				None => continue,
+			};
+			let args: Vec<&str> = tokens.collect();
+
+			match keyword {
+				"v" => {
+					let [x, y, z] = parse_floats::<3>(&args, 3, 4).map_err(err)?;
+					parser.positions.push(Point3::from(x, y, z));
+				}
+				"vn" => {
+					let [x, y, z] = parse_floats::<3>(&args, 3, 3).map_err(err)?;
+					parser.normals.push(Vec3::from(x, y, z));
+				}
+				"vt" => {
+					let [u, v] = parse_floats::<2>(&args, 1, 3).map_err(err)?;
+					parser.uvs.push((u, v));
+				}
+				"f" => parser.face(&args).map_err(err)?,
+				"g" | "o" => parser.start_group(),
+				"usemtl" => {
+					let mat_name = args
+						.first()
+						.ok_or_else(|| err("usemtl requires a material name".to_string()))?;
+					let mat = parser
+						.materials
+						.get(*mat_name)
+						.cloned()
+						.ok_or_else(|| err(format!("unknown material '{}'", mat_name)))?;
+					parser.use_material(mat);
+				}
+				"mtllib" => {
+					if args.is_empty() {
+						return Err(err("mtllib requires a file name".to_string()));
+					}
+					for lib in args.iter() {
+						let path = base_dir.join(lib);
+						let mtl_source = read(&path).map_err(|e| err(e.to_string()))?;
+						let materials = parse_mtl(&mtl_source, &path.to_string_lossy())?;
+						parser.materials.extend(materials);
+					}
+				}
+				// smoothing groups, lines, points and free-form geometry are not supported
+				_ => {}
+			}
+		}
+
+		Ok(parser.finish())
+	}
+}
+
+// vertex of a face as indices into the position, texture coordinate and normal lists
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+	mat: Arc<dyn Material>,
+	lookup: HashMap<Corner, usize>,
+	positions: Vec<Point3>,
+	normals: Vec<Option<Vec3>>,
+	uvs: Vec<Option<(f64, f64)>>,
+	faces: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+	fn from(mat: Arc<dyn Material>) -> Self {
+		Self {
+			mat,
+			lookup: HashMap::new(),
+			positions: vec![],
+			normals: vec![],
+			uvs: vec![],
+			faces: vec![],
+		}
+	}
+
+	fn build(self) -> Option<TriangleMesh> {
+		if self.faces.is_empty() {
+			return None;
+		}
+		let has_normals = self.normals.iter().any(Option::is_some);
+		let has_uvs = self.uvs.iter().any(Option::is_some);
+
+		let mut face_normals: Vec<Vec3> = vec![Vec3::new(); self.positions.len()];
+		if has_normals {
+			// vertices without an explicit normal fall back to the normal of their faces
+			for [a, b, c] in self.faces.iter() {
+				let n = cross(
+					&(self.positions[*b] - self.positions[*a]),
+					&(self.positions[*c] - self.positions[*a]),
+				);
+				for &i in [a, b, c] {
+					face_normals[i] += n;
+				}
+			}
+		}
+
+		let mut mesh = TriangleMesh::from(self.positions, self.faces, self.mat);
+		if has_normals {
+			let normals = self
+				.normals
+				.iter()
+				.zip(face_normals.iter())
+				.map(|(n, fallback)| {
+					// opposite faces may cancel out, interpolation skips a zero normal
+					n.unwrap_or_else(|| {
+						if fallback.len_squared() > 0.0 {
+							norm(fallback)
+						} else {
+							*fallback
+						}
+					})
+				})
+				.collect();
+			mesh = mesh.with_normals(normals);
+		}
+		if has_uvs {
+			mesh = mesh.with_uvs(self.uvs.iter().map(|uv| uv.unwrap_or((0.0, 0.0))).collect());
+		}
+		Some(mesh)
+	}
+}
+
+struct ObjParser {
+	positions: Vec<Point3>,
+	normals: Vec<Vec3>,
+	uvs: Vec<(f64, f64)>,
+	materials: HashMap<String, Arc<dyn Material>>,
+	current: MeshBuilder,
+	finished: Vec<Entity>,
+}
+
+impl ObjParser {
+	fn from(default_material: Arc<dyn Material>) -> Self {
+		Self {
+			positions: vec![],
+			normals: vec![],
+			uvs: vec![],
+			materials: HashMap::new(),
+			current: MeshBuilder::from(default_material),
+			finished: vec![],
+		}
+	}
+
+	fn start_group(&mut self) {
+		let mat = Arc::clone(&self.current.mat);
+		self.use_material(mat);
+	}
+
+	fn use_material(&mut self, mat: Arc<dyn Material>) {
+		let previous = std::mem::replace(&mut self.current, MeshBuilder::from(mat));
+		if let Some(mesh) = previous.build() {
+			self.finished.push(Box::new(mesh));
+		}
+	}
+
+	fn finish(mut self) -> Vec<Entity> {
+		self.start_group();
+		self.finished
+	}
+
+	fn face(&mut self, args: &[&str]) -> Result<(), String> {
+		if args.len() < 3 {
+			return Err(format!(
+				"face requires at least 3 vertices, found {}",
+				args.len()
+			));
+		}
+		let corners = args
+			.iter()
+			.map(|arg| self.corner(arg))
+			.collect::<Result<Vec<Corner>, String>>()?;
+		let vertices: Vec<usize> = corners.iter().map(|c| self.vertex(*c)).collect();
+
+		// fan triangulation, exact for the convex polygons OBJ exporters write; triangles
+		// without area are left out, they have no normal to shade with
+		let positions = &self.current.positions;
+		for i in 1..vertices.len() - 1 {
+			let [a, b, c] = [vertices[0], vertices[i], vertices[i + 1]];
+			let n = cross(
+				&(positions[b] - positions[a]),
+				&(positions[c] - positions[a]),
+			);
+			if n.len_squared() > 0.0 {
+				self.current.faces.push([a, b, c]);
+			}
+		}
+		Ok(())
+	}
+
+	fn corner(&self, arg: &str) -> Result<Corner, String> {
+		let mut parts = arg.split('/');
+		let position = resolve_index(parts.next(), self.positions.len(), "vertex")?
+			.ok_or_else(|| format!("face vertex '{}' is missing a position index", arg))?;
+		let uv = resolve_index(parts.next(), self.uvs.len(), "texture coordinate")?;
+		let normal = resolve_index(parts.next(), self.normals.len(), "normal")?;
+		if parts.next().is_some() {
+			return Err(format!("malformed face vertex '{}'", arg));
+		}
+		Ok((position, uv, normal))
+	}
+
+	fn vertex(&mut self, corner: Corner) -> usize {
+		if let Some(&i) = self.current.lookup.get(&corner) {
+			return i;
+		}
+		let (position, uv, normal) = corner;
+		let uv = uv.map(|i| self.uvs[i]);
+		let normal = normal.map(|i| norm(&self.normals[i]));
+		let mesh = &mut self.current;
+		let i = mesh.positions.len();
+		mesh.positions.push(self.positions[position]);
+		mesh.uvs.push(uv);
+		mesh.normals.push(normal);
+		mesh.lookup.insert(corner, i);
+		i
+	}
+}
+
+/// maps a MTL material library onto `Lambertian`, `Metal` and `Dielectric`
+pub fn parse_mtl(source: &str, name: &str) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+	let mut materials = HashMap::new();
+	let mut current: Option<(String, MtlDefinition)> = None;
+
+	for (i, raw) in source.lines().enumerate() {
+		let line = i + 1;
+		let err = |message: String| ObjError::Parse {
+			path: name.to_string(),
+			line,
+			message,
+		};
+		let mut tokens = strip_comment(raw).split_whitespace();
+		let keyword = match tokens.next() {
+			Some(k) => k,
+			None => continue,
+		};
+		let args: Vec<&str> = tokens.collect();
+
+		if keyword == "newmtl" {
+			let mat_name = args
+				.first()
+				.ok_or_else(|| err("newmtl requires a material name".to_string()))?;
+			if let Some((n, def)) = current.take() {
+				materials.insert(n, def.to_material());
+			}
+			current = Some((mat_name.to_string(), MtlDefinition::new()));
+			continue;
+		}
+
+		let def = match current.as_mut() {
+			Some((_, def)) => def,
+			None => {
+				return Err(err(format!(
+					"'{}' appears before any newmtl statement",
+					keyword
+				)))
+			}
+		};
+		match keyword {
+			"Kd" => def.diffuse = parse_color(&args).map_err(err)?,
+			"Ks" => def.specular = parse_color(&args).map_err(err)?,
+			"Ns" => def.shininess = parse_floats::<1>(&args, 1, 1).map_err(err)?[0],
+			"Ni" => def.refraction_index = parse_floats::<1>(&args, 1, 1).map_err(err)?[0],
+			"d" => def.dissolve = parse_floats::<1>(&args, 1, 1).map_err(err)?[0],
+			"Tr" => def.dissolve = 1.0 - parse_floats::<1>(&args, 1, 1).map_err(err)?[0],
+			"illum" => {
+				def.illum = args
+					.first()
+					.and_then(|a| a.parse::<i32>().ok())
+					.ok_or_else(|| err("illum requires an integer model".to_string()))?
+			}
+			// textures and other reflectance terms are not supported
+			_ => {}
+		}
+	}
+
+	if let Some((n, def)) = current.take() {
+		materials.insert(n, def.to_material());
+	}
+
+	Ok(materials)
+}
+
+struct MtlDefinition {
+	diffuse: Color,
+	specular: Color,
+	shininess: f64,
+	refraction_index: f64,
+	dissolve: f64,
+	illum: i32,
+}
+
+impl MtlDefinition {
+	fn new() -> Self {
+		Self {
+			diffuse: Color::from(0.8, 0.8, 0.8),
+			specular: Color::black(),
+			shininess: 0.0,
+			refraction_index: 1.5,
+			dissolve: 1.0,
+			illum: 2,
+		}
+	}
+
+	fn to_material(&self) -> Arc<dyn Material> {
+		match self.illum {
+			// refraction and glass illumination models
+			4 | 6 | 7 => Arc::new(Dielectric::from(self.refraction_index)),
+			_ if self.dissolve < 1.0 => Arc::new(Dielectric::from(self.refraction_index)),
+			// reflection illumination models
+			3 | 5 | 8 => {
+				// roughness equivalent of the phong exponent
+				let fuzz = (2.0 / (self.shininess + 2.0)).sqrt().min(1.0);
+				Arc::new(Metal::from(self.specular, fuzz))
+			}
+			_ => Arc::new(Lambertian::from(self.diffuse)),
+		}
+	}
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+	fs::read_to_string(path).map_err(|source| ObjError::Io {
+		path: PathBuf::from(path).to_string_lossy().to_string(),
+		source,
+	})
+}
+
+fn strip_comment(line: &str) -> &str {
+	match line.find('#') {
+		Some(i) => &line[..i],
+		None => line,
+	}
+}
+
+/// parses between `min` and `max` numbers, missing ones up to `N` are zero
+fn parse_floats<const N: usize>(args: &[&str], min: usize, max: usize) -> Result<[f64; N], String> {
+	if args.len() < min || args.len() > max {
+		return Err(format!(
+			"expected {} to {} numbers, found {}",
+			min,
+			max,
+			args.len()
+		));
+	}
+	let mut values = [0.0; N];
+	for (i, arg) in args.iter().enumerate().take(N) {
+		values[i] = arg
+			.parse::<f64>()
+			.map_err(|_| format!("invalid number '{}'", arg))?;
+	}
+	Ok(values)
+}
+
+fn parse_color(args: &[&str]) -> Result<Color, String> {
+	if args.first() == Some(&"spectral") || args.first() == Some(&"xyz") {
+		return Err(format!("unsupported color format '{}'", args[0]));
+	}
+	// a single value sets all channels
+	let [r, g, b] = parse_floats::<3>(args, 1, 3)?;
+	if args.len() == 1 {
+		return Ok(Color::from(r, r, r));
+	}
+	if args.len() == 2 {
+		return Err("expected 1 or 3 color components, found 2".to_string());
+	}
+	Ok(Color::from(r, g, b))
+}
+
+/// converts a one-based or negative relative OBJ index into a list index,
+/// an empty component yields `None`
+fn resolve_index(raw: Option<&str>, count: usize, kind: &str) -> Result<Option<usize>, String> {
+	let raw = match raw {
+		Some(r) if !r.is_empty() => r,
+		_ => return Ok(None),
+	};
+	let index = raw
+		.parse::<i64>()
+		.map_err(|_| format!("invalid {} index '{}'", kind, raw))?;
+	let resolved = if index > 0 {
+		index - 1
+	} else {
+		count as i64 + index
+	};
+	if index == 0 || resolved < 0 || resolved >= count as i64 {
+		return Err(format!(
+			"{} index {} out of range, {} defined so far",
+			kind, index, count
+		));
+	}
+	Ok(Some(resolved as usize))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::math::Ray;
+
+	const CUBE_FACE: &str = "
+# a quad facing +z split into two groups
+v -1 -1 0
+v 1 -1 0
+v 1 1 0
+v -1 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+g first
+f 1/1/1 2/2/1 3/3/1
+g second
+f -4//1 -2//1 -1//1
+";
+
+	fn parse(source: &str) -> Result<Vec<Entity>, ObjError> {
+		ObjLoader::new().parse(source, "test.obj", Path::new("."))
+	}
+
+	#[test]
+	fn test_groups_become_meshes() {
+		let entities = parse(CUBE_FACE).expect("valid obj");
+		assert_eq!(entities.len(), 2);
+
+		let r = Ray::from(&Point3::from(0.5, -0.5, 2.0), &Vec3::from(0.0, 0.0, -1.0));
+		let rec = entities[0].hit(&r, 0.0, 10.0).expect("hits first group");
+		assert_eq!(rec.t(), 2.0);
+		assert_eq!(rec.normal(), &Vec3::from(0.0, 0.0, 1.0));
+	}
+
+	#[test]
+	fn test_polygon_triangulation() {
+		let entities = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0.5 1.5 0\nv 0 1 0\nf 1 2 3 4 5\n")
+			.expect("valid obj");
+		assert_eq!(entities.len(), 1);
+
+		let r = Ray::from(&Point3::from(0.5, 1.2, 1.0), &Vec3::from(0.0, 0.0, -1.0));
+		assert!(entities[0].hit(&r, 0.0, 10.0).is_some());
+	}
+
+	#[test]
+	fn test_faces_without_area_are_skipped() {
+		// the second face repeats a vertex and has no normal to fall back on
+		let entities =
+			parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1 2 3//1\nf 1 1 2\n").expect("valid obj");
+		assert_eq!(entities.len(), 1);
+
+		let r = Ray::from(&Point3::from(0.2, 0.2, 1.0), &Vec3::from(0.0, 0.0, -1.0));
+		let rec = entities[0].hit(&r, 0.0, 10.0).expect("hits the triangle");
+		assert!(!rec.normal().x().is_nan());
+		assert!(parse("v 0 0 0\nv 1 0 0\nf 1 2 2\n")
+			.expect("valid obj")
+			.is_empty());
+	}
+
+	#[test]
+	fn test_errors_report_line() {
+		let err = parse("v 0 0 0\nv 1 0 0\n\nf 1 2 7\n")
+			.err()
+			.expect("invalid index");
+		assert_eq!(
+			err.to_string(),
+			"test.obj:4: vertex index 7 out of range, 2 defined so far"
+		);
+
+		let err = parse("v 0 zero 0\n").err().expect("invalid number");
+		assert_eq!(err.to_string(), "test.obj:1: invalid number 'zero'");
+
+		let err = parse("v 0 0 0\nusemtl glass\n")
+			.err()
+			.expect("unknown material");
+		assert_eq!(err.to_string(), "test.obj:2: unknown material 'glass'");
+
+		let err = parse("v 0 0 0\n\nmtllib does-not-exist.mtl\n")
+			.err()
+			.expect("missing mtl file");
+		assert!(matches!(err, ObjError::Parse { line: 3, .. }));
+		assert!(err.to_string().contains("does-not-exist.mtl"));
+	}
+
+	#[test]
+	fn test_missing_file() {
+		let err = ObjLoader::new()
+			.load("./assets/does-not-exist.obj")
+			.err()
+			.expect("missing file");
+		assert!(matches!(err, ObjError::Io { .. }));
+	}
+
+	#[test]
+	fn test_mtllib() {
+		let dir = std::env::temp_dir().join(format!(
+			"raytracing_weekend_obj_test_{}",
+			std::process::id()
+		));
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(
+			dir.join("materials.mtl"),
+			"newmtl red\nKd 1 0 0\n\nnewmtl mirror\nillum 3\nKs 0.9 0.9 0.9\nNs 1000\n\nnewmtl glass\nillum 7\nNi 1.33\n",
+		)
+		.unwrap();
+		fs::write(
+			dir.join("model.obj"),
+			"mtllib materials.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\nusemtl mirror\nf 1 2 3\nusemtl glass\nf 1 2 3\n",
+		)
+		.unwrap();
+
+		let entities = ObjLoader::new()
+			.load(&dir.join("model.obj").to_string_lossy())
+			.expect("valid obj");
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(entities.len(), 3);
+	}
+
+	#[test]
+	fn test_mtl_errors() {
+		let err = parse_mtl("Kd 1 1 1\n", "test.mtl").err().unwrap();
+		assert_eq!(
+			err.to_string(),
+			"test.mtl:1: 'Kd' appears before any newmtl statement"
+		);
+
+		let err = parse_mtl("newmtl a\nKd 1 1\n", "test.mtl").err().unwrap();
+		assert_eq!(
+			err.to_string(),
+			"test.mtl:2: expected 1 or 3 color components, found 2"
+		);
+	}
+}
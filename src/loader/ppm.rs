@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+#[derive(Debug)]
+pub enum PpmError {
+	Io {
+		path: String,
+		source: std::io::Error,
+	},
+	Parse {
+		path: String,
+		message: String,
+	},
+}
+
+impl fmt::Display for PpmError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PpmError::Io { path, source } => write!(f, "{}: {}", path, source),
+			PpmError::Parse { path, message } => write!(f, "{}: {}", path, message),
+		}
+	}
+}
+
+impl Error for PpmError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			PpmError::Io { source, .. } => Some(source),
+			PpmError::Parse { .. } => None,
+		}
+	}
+}
+
+/// decoded PPM image with channel values in `0..=max_color`, stored row by row from the top
+pub struct PpmImage {
+	pub width: usize,
+	pub height: usize,
+	pub max_color: u32,
+	pub data: Vec<[u32; 3]>,
+}
+
+/// reads ASCII (P3) and binary (P6) PPM files with 8 or 16 bits per channel
+pub fn load_ppm(path: &str) -> Result<PpmImage, PpmError> {
+	let bytes = fs::read(path).map_err(|source| PpmError::Io {
+		path: path.to_string(),
+		source,
+	})?;
+	parse_ppm(&bytes).map_err(|message| PpmError::Parse {
+		path: path.to_string(),
+		message,
+	})
+}
+
+pub fn parse_ppm(bytes: &[u8]) -> Result<PpmImage, String> {
+	let mut header = HeaderReader { bytes, pos: 0 };
+	let magic = header.token().ok_or("missing magic number")?;
+	let binary = match magic.as_str() {
+		"P3" => false,
+		"P6" => true,
+		other => return Err(format!("unsupported magic number '{}'", other)),
+	};
+	let width = header.number("width")? as usize;
+	let height = header.number("height")? as usize;
+	let max_color = header.number("max color")?;
+	if max_color == 0 || max_color > 65535 {
+		return Err(format!("max color {} out of range", max_color));
+	}
+
+	let count = width
+		.checked_mul(height)
+		.ok_or_else(|| format!("image size {}x{} out of range", width, height))?;
+	let data = if binary {
+		// exactly one whitespace byte separates the header from the raster
+		let raster = &bytes[(header.pos + 1).min(bytes.len())..];
+		let bytes_per_channel = if max_color < 256 { 1 } else { 2 };
+		// checked before allocating, the header alone may claim any size
+		if count
+			.checked_mul(3 * bytes_per_channel)
+			.is_none_or(|size| raster.len() < size)
+		{
+			return Err("unexpected end of pixel data".to_string());
+		}
+		let channel = |i: usize| -> u32 {
+			if bytes_per_channel == 1 {
+				raster[i] as u32
+			} else {
+				u16::from_be_bytes([raster[2 * i], raster[2 * i + 1]]) as u32
+			}
+		};
+		(0..count)
+			.map(|i| [channel(3 * i), channel(3 * i + 1), channel(3 * i + 2)])
+			.collect()
+	} else {
+		// every pixel takes at least six bytes, three digits and their separators
+		let mut data = Vec::with_capacity(count.min((bytes.len() - header.pos) / 6));
+		for _ in 0..count {
+			data.push([
+				header.number("red channel")?,
+				header.number("green channel")?,
+				header.number("blue channel")?,
+			]);
+		}
+		data
+	};
+
+	Ok(PpmImage {
+		width,
+		height,
+		max_color,
+		data,
+	})
+}
+
+struct HeaderReader<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+	fn token(&mut self) -> Option<String> {
+		// skip whitespace and comments
+		while self.pos < self.bytes.len() {
+			match self.bytes[self.pos] {
+				b'#' => {
+					while self.pos < self.bytes.len() && self.bytes[self.pos] != b'\n' {
+						self.pos += 1;
+					}
+				}
+				b if b.is_ascii_whitespace() => self.pos += 1,
+				_ => break,
+			}
+		}
+		let start = self.pos;
+		while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+			self.pos += 1;
+		}
+		if start == self.pos {
+			return None;
+		}
+		Some(String::from_utf8_lossy(&self.bytes[start..self.pos]).to_string())
+	}
+
+	fn number(&mut self, what: &str) -> Result<u32, String> {
+		let token = self
+			.token()
+			.ok_or_else(|| format!("unexpected end of file, expected {}", what))?;
+		token
+			.parse::<u32>()
+			.map_err(|_| format!("invalid {} '{}'", what, token))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_parse_ascii() {
+		let img = parse_ppm(b"P3\n# comment\n2 1\n255\n255 0 0\n0 128 255\n").unwrap();
+		assert_eq!((img.width, img.height, img.max_color), (2, 1, 255));
+		assert_eq!(img.data, vec![[255, 0, 0], [0, 128, 255]]);
+	}
+
+	#[test]
+	fn test_parse_binary() {
+		let mut bytes = b"P6\n1 2\n65535\n".to_vec();
+		bytes.extend_from_slice(&[0xff, 0xff, 0, 0, 0x12, 0x34, 0, 1, 0, 2, 0, 3]);
+		let img = parse_ppm(&bytes).unwrap();
+		assert_eq!(img.data, vec![[65535, 0, 0x1234], [1, 2, 3]]);
+	}
+
+	#[test]
+	fn test_parse_errors() {
+		assert_eq!(
+			parse_ppm(b"P5\n1 1\n255\n").err().unwrap(),
+			"unsupported magic number 'P5'"
+		);
+		assert_eq!(
+			parse_ppm(b"P3\n1 1\n255\n1 2\n").err().unwrap(),
+			"unexpected end of file, expected blue channel"
+		);
+		assert_eq!(
+			parse_ppm(b"P6\n2 2\n255\n\x01\x02").err().unwrap(),
+			"unexpected end of pixel data"
+		);
+		// huge headers without the pixels they announce fail without allocating
+		assert_eq!(
+			parse_ppm(b"P3 100000 100000 255").err().unwrap(),
+			"unexpected end of file, expected red channel"
+		);
+		assert_eq!(
+			parse_ppm(b"P6 100000 100000 65535\n").err().unwrap(),
+			"unexpected end of pixel data"
+		);
+	}
+}
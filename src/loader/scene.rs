@@ -0,0 +1,1008 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
+
+use super::ObjLoader;
+use crate::math::Point3;
+use crate::scene::{
+	CheckerTexture, Dielectric, DiffuseLight, ImageTexture, Lambertian, MarbleTexture, Material,
+	Metal, NoiseTexture, SolidColor, Sphere, Texture, Triangle, UvCheckerTexture, WoodTexture,
+};
+use crate::settings::{
+	Acceleration, Antialiasing, Aov, Background, DefocusBlur, Filter, RussianRoulette, Sampling,
+	ToneMapping,
+};
+use crate::{Color, Scene, Settings};
+
+const DEFAULT_MIN_SAMPLES: i32 = 16;
+
+#[derive(Debug)]
+pub enum SceneError {
+	Io {
+		path: String,
+		source: std::io::Error,
+	},
+	Parse {
+		path: String,
+		line: usize,
+		message: String,
+	},
+}
+
+impl fmt::Display for SceneError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SceneError::Io { path, source } => write!(f, "{}: {}", path, source),
+			SceneError::Parse {
+				path,
+				line,
+				message,
+			} => write!(f, "{}:{}: {}", path, line, message),
+		}
+	}
+}
+
+impl Error for SceneError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			SceneError::Io { source, .. } => Some(source),
+			SceneError::Parse { .. } => None,
+		}
+	}
+}
+
+/// Loads a TOML scene description into a scene and the settings to render it with.
+///
+/// ```toml
+/// [image]
+/// width = 400
+/// height = 300
+///
+/// [camera]
+/// look_from = [0, 1, 5]
+/// look_at = [0, 0, 0]
+/// field_of_view = 30
+/// aperture = 0.1            # enables defocus blur, focused on `look_at` by default
+///
+/// [render]
+/// samples_per_pixel = 100
+/// adaptive_threshold = 0.02   # stops sampling pixels below this relative error
+/// sampler = "sobol"           # random, stratified, halton, sobol or cmj
+/// filter = "mitchell"         # box, tent, gaussian, mitchell, lanczos or blackman_harris
+/// filter_radius = 2.0         # in pixels, every filter has a default
+/// russian_roulette = false    # paths end randomly after `roulette_min_depth` bounces by default
+/// background = [0, 0, 0]      # the sky is used without a background color
+///
+/// [display]
+/// tone_mapping = "aces"
+///
+/// [materials.ground]
+/// type = "lambertian"
+/// albedo = { type = "checker", scale = 10, even = [0.2, 0.3, 0.1], odd = [0.9, 0.9, 0.9] }
+///
+/// [[entities]]
+/// type = "sphere"
+/// center = [0, -1000, 0]
+/// radius = 1000
+/// material = "ground"
+/// ```
+///
+/// Omitted keys keep the values of `Settings::default()`, except that defocus blur is
+/// off unless an aperture is given, paths may end at random after 3 bounces and the
+/// samples come from a Sobol sequence. With an adaptive threshold `samples_per_pixel`
+/// is the most samples a pixel gets and `min_samples` the fewest, `min_samples` is an
+/// error without a threshold. Relative texture and OBJ paths are resolved from the
+/// directory of the scene file.
+pub fn load_scene(path: &str) -> Result<(Scene, Settings), SceneError> {
+	let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+		path: path.to_string(),
+		source,
+	})?;
+	let base_dir = Path::new(path)
+		.parent()
+		.map(Path::to_path_buf)
+		.unwrap_or_default();
+	parse_scene(&source, path, &base_dir)
+}
+
+/// parses a scene description, `name` is used in error messages and relative
+/// paths are resolved from `base_dir`
+pub fn parse_scene(
+	source: &str,
+	name: &str,
+	base_dir: &Path,
+) -> Result<(Scene, Settings), SceneError> {
+	let err = |span: Option<Range<usize>>, message: String| SceneError::Parse {
+		path: name.to_string(),
+		line: span.map_or(1, |s| line_of(source, s.start)),
+		message,
+	};
+	let file: SceneFile =
+		toml::from_str(source).map_err(|e| err(e.span(), e.message().to_string()))?;
+	let builder = SceneBuilder { base_dir };
+
+	let settings = builder
+		.settings(&file)
+		.map_err(|(span, message)| err(Some(span), message))?;
+	let scene = builder
+		.scene(&file)
+		.map_err(|(span, message)| err(Some(span), message))?;
+
+	Ok((scene, settings))
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+	source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+type Vector = [f64; 3];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+	#[serde(default)]
+	image: ImageDescription,
+	#[serde(default)]
+	camera: CameraDescription,
+	#[serde(default)]
+	render: RenderDescription,
+	#[serde(default)]
+	display: DisplayDescription,
+	#[serde(default)]
+	materials: BTreeMap<String, Spanned<MaterialDescription>>,
+	#[serde(default)]
+	entities: Vec<Spanned<EntityDescription>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ImageDescription {
+	width: Option<Spanned<i32>>,
+	height: Option<Spanned<i32>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+	look_from: Option<Vector>,
+	look_at: Option<Vector>,
+	field_of_view: Option<Spanned<f64>>,
+	aperture: Option<f64>,
+	focus_distance: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+	max_depth: Option<Spanned<i32>>,
+	russian_roulette: Option<bool>,
+	roulette_min_depth: Option<i32>,
+	samples_per_pixel: Option<Spanned<i32>>,
+	adaptive_threshold: Option<Spanned<f64>>,
+	min_samples: Option<Spanned<i32>>,
+	sampler: Option<SamplerDescription>,
+	filter: Option<Spanned<String>>,
+	filter_radius: Option<Spanned<f64>>,
+	acceleration: Option<AccelerationDescription>,
+	background: Option<Vector>,
+	threads: Option<usize>,
+	aovs: Option<Vec<AovDescription>>,
+	seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AccelerationDescription {
+	Bvh,
+	Linear,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SamplerDescription {
+	Random,
+	Stratified,
+	Halton,
+	Sobol,
+	Cmj,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AovDescription {
+	Albedo,
+	Normal,
+	Depth,
+	Samples,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DisplayDescription {
+	tone_mapping: Option<Spanned<ToneMappingDescription>>,
+	white_point: Option<Spanned<f64>>,
+	exposure: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ToneMappingDescription {
+	Linear,
+	Reinhard,
+	ExtendedReinhard,
+	Aces,
+	Uncharted2,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+	Lambertian {
+		albedo: TextureDescription,
+	},
+	Metal {
+		albedo: TextureDescription,
+		#[serde(default)]
+		fuzz: f64,
+	},
+	Dielectric {
+		refraction_index: f64,
+	},
+	DiffuseLight {
+		emit: TextureDescription,
+	},
+}
+
+/// either a plain `[r, g, b]` color or a table describing a texture
+enum TextureDescription {
+	Color(Vector),
+	Texture(TextureKind),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureKind {
+	Checker {
+		scale: f64,
+		even: Box<TextureDescription>,
+		odd: Box<TextureDescription>,
+	},
+	UvChecker {
+		columns: u32,
+		rows: u32,
+		even: Box<TextureDescription>,
+		odd: Box<TextureDescription>,
+	},
+	Image {
+		path: String,
+	},
+	Noise {
+		seed: u64,
+		scale: f64,
+		low: Vector,
+		high: Vector,
+	},
+	Marble {
+		seed: u64,
+		scale: f64,
+		base: Vector,
+		vein: Vector,
+	},
+	Wood {
+		seed: u64,
+		rings_per_unit: f64,
+		light: Vector,
+		dark: Vector,
+	},
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum EntityDescription {
+	Sphere {
+		center: Vector,
+		radius: f64,
+		material: String,
+	},
+	Triangle {
+		vertices: [Vector; 3],
+		material: String,
+	},
+	/// axis aligned rectangle between two corners sharing one coordinate
+	Rectangle {
+		min: Vector,
+		max: Vector,
+		material: String,
+	},
+	/// axis aligned box between two opposite corners
+	Cuboid {
+		min: Vector,
+		max: Vector,
+		material: String,
+	},
+	/// Wavefront OBJ file, `material` replaces the default of faces without one
+	Obj {
+		path: String,
+		material: Option<String>,
+	},
+}
+
+impl<'de> Deserialize<'de> for TextureDescription {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct TextureVisitor;
+
+		impl<'de> Visitor<'de> for TextureVisitor {
+			type Value = TextureDescription;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a color [r, g, b] or a texture table")
+			}
+
+			fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+				Vector::deserialize(SeqAccessDeserializer::new(seq)).map(TextureDescription::Color)
+			}
+
+			fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+				TextureKind::deserialize(MapAccessDeserializer::new(map))
+					.map(TextureDescription::Texture)
+			}
+		}
+
+		deserializer.deserialize_any(TextureVisitor)
+	}
+}
+
+type BuildResult<T> = Result<T, (Range<usize>, String)>;
+
+struct SceneBuilder<'a> {
+	base_dir: &'a Path,
+}
+
+fn point([x, y, z]: Vector) -> Point3 {
+	Point3::from(x, y, z)
+}
+
+fn color([r, g, b]: Vector) -> Color {
+	Color::from(r, g, b)
+}
+
+impl<'a> SceneBuilder<'a> {
+	fn settings(&self, file: &SceneFile) -> BuildResult<Settings> {
+		let mut settings = Settings::default();
+		settings.rendering.russian_roulette = RussianRoulette::ON { min_depth: 3 };
+		settings.rendering.sampling = Sampling::SOBOL;
+
+		for size in [&file.image.width, &file.image.height]
+			.iter()
+			.copied()
+			.flatten()
+		{
+			if *size.get_ref() < 2 {
+				return Err((
+					size.span(),
+					"image dimensions must be at least 2".to_string(),
+				));
+			}
+		}
+		if let Some(width) = &file.image.width {
+			settings.image.width = *width.get_ref();
+		}
+		if let Some(height) = &file.image.height {
+			settings.image.height = *height.get_ref();
+		}
+
+		let camera = &file.camera;
+		if let Some(look_from) = camera.look_from {
+			settings.camera.look_from = point(look_from);
+		}
+		if let Some(look_at) = camera.look_at {
+			settings.camera.look_at = point(look_at);
+		}
+		if let Some(field_of_view) = &camera.field_of_view {
+			let degrees = *field_of_view.get_ref();
+			if !(degrees > 0.0 && degrees < 180.0) {
+				return Err((
+					field_of_view.span(),
+					"field_of_view must be between 0 and 180 degrees".to_string(),
+				));
+			}
+			settings.camera.field_of_view = degrees;
+		}
+		settings.rendering.blur = match camera.aperture {
+			Some(aperture) if aperture > 0.0 => DefocusBlur::ON {
+				aperture,
+				focus_distance: camera
+					.focus_distance
+					.unwrap_or_else(|| (settings.camera.look_from - settings.camera.look_at).len()),
+			},
+			_ => DefocusBlur::OFF,
+		};
+
+		let render = &file.render;
+		if let Some(max_depth) = &render.max_depth {
+			if *max_depth.get_ref() < 1 {
+				return Err((max_depth.span(), "max_depth must be positive".to_string()));
+			}
+			settings.rendering.max_depth = *max_depth.get_ref();
+		}
+		if let Some(min_depth) = render.roulette_min_depth {
+			settings.rendering.russian_roulette = RussianRoulette::ON { min_depth };
+		}
+		if render.russian_roulette == Some(false) {
+			settings.rendering.russian_roulette = RussianRoulette::OFF;
+		}
+		if let Some(samples) = &render.samples_per_pixel {
+			let samples_per_pixel = *samples.get_ref();
+			if samples_per_pixel < 1 {
+				return Err((
+					samples.span(),
+					"samples_per_pixel must be at least 1".to_string(),
+				));
+			}
+			settings.rendering.antialiasing = Antialiasing::MSAA { samples_per_pixel };
+		}
+		if let (Some(min_samples), None) = (&render.min_samples, &render.adaptive_threshold) {
+			return Err((
+				min_samples.span(),
+				"min_samples requires an adaptive_threshold".to_string(),
+			));
+		}
+		if let Some(threshold) = &render.adaptive_threshold {
+			if *threshold.get_ref() <= 0.0 {
+				return Err((
+					threshold.span(),
+					"adaptive_threshold must be positive".to_string(),
+				));
+			}
+			let max_samples = match settings.antialiasing() {
+				Antialiasing::MSAA { samples_per_pixel } => *samples_per_pixel,
+				_ => 1,
+			};
+			let min_samples = match &render.min_samples {
+				Some(min_samples) => {
+					let value = *min_samples.get_ref();
+					if value < 1 {
+						return Err((
+							min_samples.span(),
+							"min_samples must be at least 1".to_string(),
+						));
+					}
+					if value > max_samples {
+						return Err((
+							min_samples.span(),
+							"min_samples must not exceed samples_per_pixel".to_string(),
+						));
+					}
+					value
+				}
+				None => DEFAULT_MIN_SAMPLES.min(max_samples),
+			};
+			settings.rendering.antialiasing = Antialiasing::ADAPTIVE {
+				min_samples,
+				max_samples,
+				threshold: *threshold.get_ref(),
+			};
+		}
+		if let Some(sampler) = &render.sampler {
+			settings.rendering.sampling = match sampler {
+				SamplerDescription::Random => Sampling::RANDOM,
+				SamplerDescription::Stratified => Sampling::STRATIFIED,
+				SamplerDescription::Halton => Sampling::HALTON,
+				SamplerDescription::Sobol => Sampling::SOBOL,
+				SamplerDescription::Cmj => Sampling::CMJ,
+			};
+		}
+		if let Some(name) = &render.filter {
+			settings.rendering.filter = Filter::from_name(name.get_ref())
+				.ok_or_else(|| (name.span(), format!("unknown filter '{}'", name.get_ref())))?;
+		}
+		if let Some(radius) = &render.filter_radius {
+			if *radius.get_ref() <= 0.0 {
+				return Err((radius.span(), "filter_radius must be positive".to_string()));
+			}
+			settings.rendering.filter = settings.rendering.filter.with_radius(*radius.get_ref());
+		}
+		if let Some(acceleration) = &render.acceleration {
+			settings.rendering.acceleration = match acceleration {
+				AccelerationDescription::Bvh => Acceleration::BVH,
+				AccelerationDescription::Linear => Acceleration::LINEAR,
+			};
+		}
+		if let Some(color) = render.background {
+			settings.rendering.background = Background::SOLID {
+				color: self::color(color),
+			};
+		}
+		if let Some(threads) = render.threads {
+			settings.rendering.threads = threads;
+		}
+		if let Some(seed) = render.seed {
+			settings.rendering.seed = seed;
+		}
+		if let Some(aovs) = &render.aovs {
+			settings.rendering.aovs = aovs
+				.iter()
+				.map(|aov| match aov {
+					AovDescription::Albedo => Aov::ALBEDO,
+					AovDescription::Normal => Aov::NORMAL,
+					AovDescription::Depth => Aov::DEPTH,
+					AovDescription::Samples => Aov::SAMPLES,
+				})
+				.collect();
+		}
+
+		let display = &file.display;
+		if let Some(white_point) = &display.white_point {
+			if *white_point.get_ref() <= 0.0 {
+				return Err((
+					white_point.span(),
+					"white_point must be positive".to_string(),
+				));
+			}
+		}
+		if let Some(tone_mapping) = &display.tone_mapping {
+			settings.display.tone_mapping = match tone_mapping.get_ref() {
+				ToneMappingDescription::Linear => ToneMapping::LINEAR,
+				ToneMappingDescription::Reinhard => ToneMapping::REINHARD,
+				ToneMappingDescription::ExtendedReinhard => ToneMapping::EXTENDED_REINHARD {
+					white_point: *display
+						.white_point
+						.as_ref()
+						.ok_or_else(|| {
+							(
+								tone_mapping.span(),
+								"extended_reinhard needs a white_point".to_string(),
+							)
+						})?
+						.get_ref(),
+				},
+				ToneMappingDescription::Aces => ToneMapping::ACES,
+				ToneMappingDescription::Uncharted2 => ToneMapping::UNCHARTED2,
+			};
+		}
+		if let Some(exposure) = display.exposure {
+			settings.display.exposure = exposure;
+		}
+
+		Ok(settings)
+	}
+
+	fn scene(&self, file: &SceneFile) -> BuildResult<Scene> {
+		let mut materials: BTreeMap<&str, Arc<dyn Material>> = BTreeMap::new();
+		for (name, description) in file.materials.iter() {
+			materials.insert(name, self.material(description)?);
+		}
+		let mut scene = Scene::new();
+		for (index, entity) in file.entities.iter().enumerate() {
+			// serde buffers tagged enums, so errors can only point at the whole entity
+			let material = |name: &str| -> BuildResult<Arc<dyn Material>> {
+				materials.get(name).cloned().ok_or_else(|| {
+					(
+						entity.span(),
+						format!("entities[{}].material: unknown material '{}'", index, name),
+					)
+				})
+			};
+			match entity.get_ref() {
+				EntityDescription::Sphere {
+					center,
+					radius,
+					material: name,
+				} => scene.add_entity(Box::new(Sphere::from(
+					point(*center),
+					*radius,
+					material(name)?,
+				))),
+				EntityDescription::Triangle {
+					vertices: [a, b, c],
+					material: name,
+				} => scene.add_entity(Box::new(Triangle::from(
+					point(*a),
+					point(*b),
+					point(*c),
+					material(name)?,
+				))),
+				EntityDescription::Rectangle {
+					min,
+					max,
+					material: name,
+				} => {
+					if !(0..3).any(|axis| min[axis] == max[axis]) {
+						return Err((
+							entity.span(),
+							format!(
+								"entities[{}]: rectangle corners have to share one coordinate",
+								index
+							),
+						));
+					}
+					scene.add_entity(Box::new(Scene::rectangle(
+						point(*min),
+						point(*max),
+						material(name)?,
+					)))
+				}
+				EntityDescription::Cuboid {
+					min,
+					max,
+					material: name,
+				} => scene.add_entity(Box::new(Scene::cuboid(
+					point(*min),
+					point(*max),
+					material(name)?,
+				))),
+				EntityDescription::Obj {
+					path,
+					material: name,
+				} => {
+					let loader = match name {
+						Some(name) => ObjLoader::from(material(name)?),
+						None => ObjLoader::new(),
+					};
+					let path = self.base_dir.join(path);
+					let meshes = loader
+						.load(&path.to_string_lossy())
+						.map_err(|e| (entity.span(), format!("entities[{}]: {}", index, e)))?;
+					for mesh in meshes.into_iter() {
+						scene.add_entity(mesh);
+					}
+				}
+			}
+		}
+
+		Ok(scene)
+	}
+
+	fn material(
+		&self,
+		description: &Spanned<MaterialDescription>,
+	) -> BuildResult<Arc<dyn Material>> {
+		let texture = |texture| {
+			self.texture(texture)
+				.map_err(|message| (description.span(), message))
+		};
+		Ok(match description.get_ref() {
+			MaterialDescription::Lambertian { albedo } => {
+				Arc::new(Lambertian::from_texture(texture(albedo)?))
+			}
+			MaterialDescription::Metal { albedo, fuzz } => {
+				// serde buffers tagged enums, so the error points at the whole material
+				if !(0.0..=1.0).contains(fuzz) {
+					return Err((
+						description.span(),
+						"fuzz must be between 0 and 1".to_string(),
+					));
+				}
+				Arc::new(Metal::from_texture(texture(albedo)?, *fuzz))
+			}
+			MaterialDescription::Dielectric { refraction_index } => {
+				if *refraction_index <= 0.0 {
+					return Err((
+						description.span(),
+						"refraction_index must be positive".to_string(),
+					));
+				}
+				Arc::new(Dielectric::from(*refraction_index))
+			}
+			MaterialDescription::DiffuseLight { emit } => {
+				Arc::new(DiffuseLight::from_texture(texture(emit)?))
+			}
+		})
+	}
+
+	fn texture(&self, description: &TextureDescription) -> Result<Arc<dyn Texture>, String> {
+		let kind = match description {
+			TextureDescription::Color(color) => {
+				return Ok(Arc::new(SolidColor::from(self::color(*color))));
+			}
+			TextureDescription::Texture(kind) => kind,
+		};
+
+		Ok(match kind {
+			TextureKind::Checker { scale, even, odd } => Arc::new(CheckerTexture::from(
+				*scale,
+				self.texture(even)?,
+				self.texture(odd)?,
+			)),
+			TextureKind::UvChecker {
+				columns,
+				rows,
+				even,
+				odd,
+			} => Arc::new(UvCheckerTexture::from(
+				*columns,
+				*rows,
+				self.texture(even)?,
+				self.texture(odd)?,
+			)),
+			TextureKind::Image { path } => {
+				let path = self.base_dir.join(path);
+				Arc::new(ImageTexture::load(&path.to_string_lossy()).map_err(|e| e.to_string())?)
+			}
+			TextureKind::Noise {
+				seed,
+				scale,
+				low,
+				high,
+			} => Arc::new(NoiseTexture::from(*seed, *scale, color(*low), color(*high))),
+			TextureKind::Marble {
+				seed,
+				scale,
+				base,
+				vein,
+			} => Arc::new(MarbleTexture::from(
+				*seed,
+				*scale,
+				color(*base),
+				color(*vein),
+			)),
+			TextureKind::Wood {
+				seed,
+				rings_per_unit,
+				light,
+				dark,
+			} => Arc::new(WoodTexture::from(
+				*seed,
+				*rings_per_unit,
+				color(*light),
+				color(*dark),
+			)),
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::math::{Ray, Vec3};
+
+	fn parse(source: &str) -> Result<(Scene, Settings), SceneError> {
+		parse_scene(source, "test.toml", Path::new("."))
+	}
+
+	fn error(source: &str) -> String {
+		parse(source)
+			.err()
+			.expect("parsing should fail")
+			.to_string()
+	}
+
+	#[test]
+	fn test_parse_scene() {
+		let (scene, settings) = parse(
+			r#"
+[image]
+width = 64
+height = 32
+
+[camera]
+look_from = [0, 0, 5]
+field_of_view = 30
+aperture = 0.5
+
+[render]
+samples_per_pixel = 8
+sampler = "cmj"
+filter = "gaussian"
+filter_radius = 2
+roulette_min_depth = 5
+seed = 11
+acceleration = "linear"
+background = [0.1, 0.2, 0.3]
+aovs = ["normal"]
+
+[display]
+tone_mapping = "extended_reinhard"
+white_point = 4.0
+exposure = -1
+
+[materials.red]
+type = "lambertian"
+albedo = [1, 0, 0]
+
+[materials.floor]
+type = "metal"
+albedo = { type = "checker", scale = 2, even = [1, 1, 1], odd = { type = "noise", seed = 3, scale = 1, low = [0, 0, 0], high = [1, 1, 1] } }
+
+[[entities]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "red"
+
+[[entities]]
+type = "rectangle"
+min = [-5, -1, -5]
+max = [5, -1, 5]
+material = "floor"
+"#,
+		)
+		.unwrap();
+
+		assert_eq!((settings.width(), settings.height()), (64, 32));
+		assert_eq!(settings.camera.field_of_view, 30.0);
+		assert!(matches!(
+			settings.defocus_blur(),
+			DefocusBlur::ON { aperture, focus_distance } if *aperture == 0.5 && *focus_distance == 5.0
+		));
+		assert!(matches!(
+			settings.antialiasing(),
+			Antialiasing::MSAA {
+				samples_per_pixel: 8
+			}
+		));
+		assert!(matches!(settings.acceleration(), Acceleration::LINEAR));
+		assert!(matches!(settings.background(), Background::SOLID { .. }));
+		assert_eq!(settings.aovs(), &[Aov::NORMAL]);
+		assert_eq!(settings.sampling(), &Sampling::CMJ);
+		assert_eq!(
+			settings.filter(),
+			&Filter::GAUSSIAN {
+				radius: 2.0,
+				alpha: 2.0
+			}
+		);
+		assert_eq!(
+			settings.russian_roulette(),
+			&RussianRoulette::ON { min_depth: 5 }
+		);
+		assert!(matches!(
+			settings.tone_mapping(),
+			ToneMapping::EXTENDED_REINHARD { white_point } if *white_point == 4.0
+		));
+		assert_eq!(settings.exposure(), -1.0);
+		assert_eq!(settings.seed(), 11);
+		assert_eq!(settings.max_depth(), Settings::default().max_depth());
+
+		let down = Ray::from(&Point3::from(3.0, 5.0, 0.0), &Vec3::from(0.0, -1.0, 0.0));
+		assert_eq!(
+			scene.intersect(&down, 0.001, f64::INFINITY).unwrap().t(),
+			6.0
+		);
+		let forward = Ray::from(&Point3::from(0.0, 0.0, 5.0), &Vec3::from(0.0, 0.0, -1.0));
+		assert_eq!(
+			scene.intersect(&forward, 0.001, f64::INFINITY).unwrap().t(),
+			4.0
+		);
+	}
+
+	#[test]
+	fn test_defaults() {
+		let (_, settings) = parse("").unwrap();
+		assert_eq!(settings.width(), Settings::default().width());
+		assert!(matches!(settings.defocus_blur(), DefocusBlur::OFF));
+		assert!(matches!(settings.background(), Background::SKY));
+		assert_eq!(
+			settings.russian_roulette(),
+			&RussianRoulette::ON { min_depth: 3 }
+		);
+		assert_eq!(settings.sampling(), &Sampling::SOBOL);
+	}
+
+	#[test]
+	fn test_errors_point_to_the_offending_line() {
+		assert_eq!(
+			error("[image]\nwidth = 10\n\n[[entities]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"glas\"\n"),
+			"test.toml:4: entities[0].material: unknown material 'glas'"
+		);
+		assert_eq!(
+			error("[image]\nwidth = 10\nheight = \"tall\"\n"),
+			"test.toml:3: invalid type: string \"tall\", expected i32"
+		);
+		assert!(error("[camera]\nlook_from = [0, 0, 5]\nzoom = 2\n")
+			.starts_with("test.toml:3: unknown field `zoom`"));
+		assert_eq!(
+			error("[display]\n\ntone_mapping = \"extended_reinhard\"\n"),
+			"test.toml:3: extended_reinhard needs a white_point"
+		);
+		assert_eq!(
+			error("[materials.a]\ntype = \"lambertian\"\nalbedo = [1, 1, 1]\n[[entities]]\ntype = \"rectangle\"\nmin = [0, 0, 0]\nmax = [1, 1, 1]\nmaterial = \"a\"\n"),
+			"test.toml:4: entities[0]: rectangle corners have to share one coordinate"
+		);
+		assert!(error("[render]\nsamples_per_pixel = 0\n").starts_with("test.toml:2: "));
+		assert_eq!(
+			error("[image]\nwidth = 10\n\n[materials.brushed]\ntype = \"metal\"\nalbedo = [1, 1, 1]\nfuzz = 1.5\n"),
+			"test.toml:4: fuzz must be between 0 and 1"
+		);
+		assert_eq!(
+			error("[image]\nwidth = 10\n\n[materials.glass]\ntype = \"dielectric\"\nrefraction_index = 0\n"),
+			"test.toml:4: refraction_index must be positive"
+		);
+		assert_eq!(
+			error("[render]\nmax_depth = 0\n"),
+			"test.toml:2: max_depth must be positive"
+		);
+		assert_eq!(
+			error("[camera]\nfield_of_view = 180\n"),
+			"test.toml:2: field_of_view must be between 0 and 180 degrees"
+		);
+		assert_eq!(
+			error("[display]\ntone_mapping = \"extended_reinhard\"\nwhite_point = -1.0\n"),
+			"test.toml:3: white_point must be positive"
+		);
+		assert_eq!(
+			error("[render]\nsamples_per_pixel = 8\nadaptive_threshold = 0\n"),
+			"test.toml:3: adaptive_threshold must be positive"
+		);
+		assert_eq!(
+			error("[render]\nsamples_per_pixel = 8\nmin_samples = 4\n"),
+			"test.toml:3: min_samples requires an adaptive_threshold"
+		);
+		assert_eq!(
+			error("[render]\nsamples_per_pixel = 8\nmin_samples = 12\nadaptive_threshold = 0.05\n"),
+			"test.toml:3: min_samples must not exceed samples_per_pixel"
+		);
+		assert_eq!(
+			error("[render]\nsamples_per_pixel = 8\nmin_samples = 0\nadaptive_threshold = 0.05\n"),
+			"test.toml:3: min_samples must be at least 1"
+		);
+		assert_eq!(
+			error("[render]\nfilter = \"sinc\"\n"),
+			"test.toml:2: unknown filter 'sinc'"
+		);
+	}
+
+	#[test]
+	fn test_adaptive_sampling() {
+		let (_, settings) = parse(
+			"[render]\nsamples_per_pixel = 64\nadaptive_threshold = 0.05\naovs = [\"samples\"]\n",
+		)
+		.unwrap();
+		assert!(matches!(
+			settings.antialiasing(),
+			Antialiasing::ADAPTIVE { min_samples: 16, max_samples: 64, threshold } if *threshold == 0.05
+		));
+		assert_eq!(settings.aovs(), &[Aov::SAMPLES]);
+
+		let (_, settings) =
+			parse("[render]\nsamples_per_pixel = 8\nmin_samples = 4\nadaptive_threshold = 0.05\n")
+				.unwrap();
+		assert!(matches!(
+			settings.antialiasing(),
+			Antialiasing::ADAPTIVE {
+				min_samples: 4,
+				max_samples: 8,
+				..
+			}
+		));
+		// the default minimum gives way to fewer samples per pixel
+		let (_, settings) =
+			parse("[render]\nsamples_per_pixel = 8\nadaptive_threshold = 0.05\n").unwrap();
+		assert!(matches!(
+			settings.antialiasing(),
+			Antialiasing::ADAPTIVE {
+				min_samples: 8,
+				max_samples: 8,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn test_load_example() {
+		let (scene, settings) = load_scene("./assets/scenes/cornell_box.toml").unwrap();
+		assert_eq!(settings.camera.field_of_view, 40.0);
+		let r = Ray::from(
+			&Point3::from(278.0, 278.0, -800.0),
+			&Vec3::from(0.0, 0.0, 1.0),
+		);
+		assert!(scene.intersect(&r, 0.001, f64::INFINITY).is_some());
+	}
+
+	#[test]
+	fn test_load_missing_file() {
+		assert!(matches!(
+			load_scene("./assets/scenes/does-not-exist.toml"),
+			Err(SceneError::Io { .. })
+		));
+	}
+}
@@ -1,9 +1,205 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
 use raytracing_weekend::{
-    Antialiasing, ImageSettings, PPMWriter, RenderSettings, Renderer, Scene, Settings, WriteResult,
+    load_scene, writer_for_format, writer_for_path, Antialiasing, Aov, Checkpoint, DefocusBlur,
+    Filter, ImageSettings, Point3, Progressive, RenderSettings, Renderer, RussianRoulette,
+    Sampling, Scene, Settings,
 };
 
+const USAGE: &str = "\
+Usage: raytracing_weekend [OPTIONS]
+
+Renders the random sphere scene or a TOML scene file. Flags override the
+values of the scene file.
+
+Options:
+      --scene <FILE>            TOML scene description to render
+  -o, --output <FILE>           output path [default: ./assets/default.ppm]
+      --format <FORMAT>         ppm, png, pfm or exr [default: output extension]
+  -W, --width <PIXELS>          image width [default: 400]
+  -H, --height <PIXELS>         image height [default: 280]
+  -s, --samples <N>             samples per pixel and passes, the maximum when adaptive [default: 50]
+      --adaptive <THRESHOLD>    stop sampling pixels once their relative error is below
+      --min-samples <N>         samples per pixel before adaptive sampling may stop, needs
+                                --adaptive or an adaptive scene [default: 16]
+      --heatmap <FILE>          also write the number of samples of every pixel
+      --time-limit <SECONDS>    render progressively and stop starting passes after this time
+      --noise-target <ERROR>    render progressively until the mean relative error is below
+      --preview <FILE>          render progressively and write the image after every pass
+      --preview-every <SECONDS> least time between two previews [default: 0]
+      --checkpoint <FILE>       render progressively, saving the state to FILE and resuming
+                                from it when it exists; edits to OBJ or texture files of
+                                the scene are not detected
+      --checkpoint-every <SECONDS>
+                                least time between two checkpoints [default: 0]
+      --sampler <NAME>          random, stratified, halton, sobol or cmj [default: sobol]
+      --filter <NAME>           box, tent, gaussian, mitchell, lanczos or blackman_harris
+      --filter-radius <PIXELS>  reach of the pixel filter, every filter has its own default
+      --max-depth <N>           maximum number of bounces [default: 50]
+      --russian-roulette <N>    bounces before paths may end at random, or off [default: 3]
+      --look-from <X,Y,Z>       camera position
+      --look-at <X,Y,Z>         point the camera looks at
+      --fov <DEGREES>           vertical field of view, between 0 and 180
+      --aperture <DIAMETER>     lens aperture, 0 disables defocus blur
+      --focus-distance <DIST>   distance of the focal plane
+      --seed <N>                seed of the samples and the random sphere scene [default: 0]
+  -j, --threads <N>             worker threads, 0 uses all cores [default: 0]
+  -h, --help                    print this help
+";
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    scene: Option<String>,
+    output: Option<String>,
+    format: Option<String>,
+    width: Option<i32>,
+    height: Option<i32>,
+    samples: Option<i32>,
+    adaptive: Option<f64>,
+    min_samples: Option<i32>,
+    heatmap: Option<String>,
+    time_limit: Option<f64>,
+    noise_target: Option<f64>,
+    preview: Option<String>,
+    preview_every: Option<f64>,
+    checkpoint: Option<String>,
+    checkpoint_every: Option<f64>,
+    sampling: Option<Sampling>,
+    filter: Option<Filter>,
+    filter_radius: Option<f64>,
+    max_depth: Option<i32>,
+    russian_roulette: Option<RussianRoulette>,
+    look_from: Option<Point3>,
+    look_at: Option<Point3>,
+    field_of_view: Option<f64>,
+    aperture: Option<f64>,
+    focus_distance: Option<f64>,
+    seed: Option<u64>,
+    threads: Option<usize>,
+}
+
+impl Options {
+    /// limits of a progressive render, `None` unless a flag asks for one
+    fn progressive(&self) -> Option<Progressive> {
+        if self.time_limit.is_none()
+            && self.noise_target.is_none()
+            && self.preview.is_none()
+            && self.checkpoint.is_none()
+        {
+            return None;
+        }
+        Some(Progressive {
+            time_limit: self.time_limit.map(Duration::from_secs_f64),
+            noise_target: self.noise_target,
+            preview_interval: self.preview_every.map(Duration::from_secs_f64),
+            checkpoint: self.checkpoint.clone(),
+            checkpoint_interval: self.checkpoint_every.map(Duration::from_secs_f64),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Render(Box<Options>),
+    Help,
+}
+
 fn main() {
-    let settings = Settings {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Render(options)) => *options,
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// identifies a scene file in checkpoints by an FNV-1a hash of its source, the OBJ and
+/// texture files it references are left out
+fn scene_id(path: &str) -> Result<String, Box<dyn Error>> {
+    let hash = fs::read(path)?
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    Ok(format!("{:016x}", hash))
+}
+
+fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let (scene, mut settings, scene_id) = match &options.scene {
+        Some(path) => {
+            let (scene, settings) = load_scene(path)?;
+            (scene, settings, scene_id(path)?)
+        }
+        None => {
+            let seed = options.seed.unwrap_or_default();
+            (
+                Scene::random(seed),
+                default_settings(),
+                format!("random {}", seed),
+            )
+        }
+    };
+    apply(&options, &mut settings)?;
+
+    let output = options.output.as_deref().unwrap_or("./assets/default.ppm");
+    let writer = match &options.format {
+        Some(format) => writer_for_format(format, output)?,
+        None => writer_for_path(output)?,
+    };
+    let heatmap_writer = match &options.heatmap {
+        Some(path) => {
+            if !settings.rendering.aovs.contains(&Aov::SAMPLES) {
+                settings.rendering.aovs.push(Aov::SAMPLES);
+            }
+            Some(writer_for_path(path)?)
+        }
+        None => None,
+    };
+
+    let renderer = Renderer::from(scene, settings);
+    let result = match options.progressive() {
+        Some(mut progressive) => {
+            progressive.scene = scene_id;
+            let preview = match &options.preview {
+                Some(path) => Some(writer_for_path(path)?),
+                None => None,
+            };
+            match &options.checkpoint {
+                Some(path) if Path::new(path).exists() => {
+                    let checkpoint = Checkpoint::load(path)?;
+                    eprintln!("resuming after pass {} of {}", checkpoint.passes(), path);
+                    renderer.resume_progressive(checkpoint, &progressive, preview.as_deref())?
+                }
+                _ => renderer.render_progressive(&progressive, preview.as_deref())?,
+            }
+        }
+        None => renderer.render(),
+    };
+    if let Some(heatmap_writer) = heatmap_writer {
+        let heatmap = result.heatmap().ok_or("no sample counts were recorded")?;
+        heatmap_writer.write(heatmap)?;
+    }
+    writer.write(result)
+}
+
+fn default_settings() -> Settings {
+    Settings {
         image: ImageSettings {
             width: 400,
             height: 280,
@@ -12,14 +208,484 @@ fn main() {
             antialiasing: Antialiasing::MSAA {
                 samples_per_pixel: 50,
             },
+            russian_roulette: RussianRoulette::ON { min_depth: 3 },
+            sampling: Sampling::SOBOL,
             ..Default::default()
         },
         ..Default::default()
+    }
+}
+
+fn apply(options: &Options, settings: &mut Settings) -> Result<(), String> {
+    if let Some(width) = options.width {
+        settings.image.width = width;
+    }
+    if let Some(height) = options.height {
+        settings.image.height = height;
+    }
+    if let Some(samples_per_pixel) = options.samples {
+        settings.rendering.antialiasing = match settings.rendering.antialiasing {
+            Antialiasing::ADAPTIVE {
+                min_samples,
+                threshold,
+                ..
+            } => Antialiasing::ADAPTIVE {
+                min_samples,
+                max_samples: samples_per_pixel,
+                threshold,
+            },
+            _ => Antialiasing::MSAA { samples_per_pixel },
+        };
+    }
+    if let Some(threshold) = options.adaptive {
+        let (min_samples, max_samples) = match settings.rendering.antialiasing {
+            Antialiasing::MSAA { samples_per_pixel } => (16, samples_per_pixel),
+            Antialiasing::ADAPTIVE {
+                min_samples,
+                max_samples,
+                ..
+            } => (min_samples, max_samples),
+            Antialiasing::NONE => (1, 1),
+        };
+        settings.rendering.antialiasing = Antialiasing::ADAPTIVE {
+            min_samples,
+            max_samples,
+            threshold,
+        };
+    }
+    match &mut settings.rendering.antialiasing {
+        Antialiasing::ADAPTIVE {
+            min_samples,
+            max_samples,
+            ..
+        } => {
+            *min_samples = options
+                .min_samples
+                .unwrap_or(*min_samples)
+                .min(*max_samples)
+        }
+        _ if options.min_samples.is_some() => {
+            return Err("--min-samples requires --adaptive or an adaptive scene".to_string())
+        }
+        _ => {}
+    }
+    if let Some(sampling) = options.sampling {
+        settings.rendering.sampling = sampling;
+    }
+    if let Some(filter) = options.filter {
+        settings.rendering.filter = filter;
+    }
+    if let Some(radius) = options.filter_radius {
+        settings.rendering.filter = settings.rendering.filter.with_radius(radius);
+    }
+    if let Some(max_depth) = options.max_depth {
+        settings.rendering.max_depth = max_depth;
+    }
+    if let Some(russian_roulette) = options.russian_roulette {
+        settings.rendering.russian_roulette = russian_roulette;
+    }
+    if let Some(look_from) = options.look_from {
+        settings.camera.look_from = look_from;
+    }
+    if let Some(look_at) = options.look_at {
+        settings.camera.look_at = look_at;
+    }
+    if let Some(field_of_view) = options.field_of_view {
+        settings.camera.field_of_view = field_of_view;
+    }
+    if let Some(threads) = options.threads {
+        settings.rendering.threads = threads;
+    }
+    if let Some(seed) = options.seed {
+        settings.rendering.seed = seed;
+    }
+
+    // without camera flags the focus of the scene file stays, otherwise it follows the
+    // camera onto `look_at` like in scene files
+    if options.look_from.is_none()
+        && options.look_at.is_none()
+        && options.aperture.is_none()
+        && options.focus_distance.is_none()
+    {
+        return Ok(());
+    }
+    let aperture = match settings.rendering.blur {
+        DefocusBlur::ON { aperture, .. } => options.aperture.or(Some(aperture)),
+        DefocusBlur::OFF => options.aperture,
+    };
+    let focus_distance = options
+        .focus_distance
+        .unwrap_or_else(|| (settings.camera.look_from - settings.camera.look_at).len());
+    settings.rendering.blur = match aperture {
+        Some(aperture) if aperture > 0.0 => DefocusBlur::ON {
+            aperture,
+            focus_distance,
+        },
+        _ => DefocusBlur::OFF,
     };
+    Ok(())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        // accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        if flag == "-h" || flag == "--help" {
+            return Ok(Command::Help);
+        }
+        let mut value = || -> Result<String, String> {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", flag))
+        };
 
-    let renderer = Renderer::from(Scene::random(), settings);
-    let result = renderer.render();
+        match flag.as_str() {
+            "--scene" => options.scene = Some(value()?),
+            "-o" | "--output" => options.output = Some(value()?),
+            "--format" => options.format = Some(value()?),
+            "-W" | "--width" => options.width = Some(dimension(&flag, &value()?)?),
+            "-H" | "--height" => options.height = Some(dimension(&flag, &value()?)?),
+            "-s" | "--samples" => options.samples = Some(positive(&flag, &value()?)?),
+            "--adaptive" => {
+                options.adaptive = match number(&flag, &value()?)? {
+                    t if t > 0.0 => Some(t),
+                    _ => return Err(format!("{} must be positive", flag)),
+                }
+            }
+            "--min-samples" => options.min_samples = Some(positive(&flag, &value()?)?),
+            "--heatmap" => options.heatmap = Some(value()?),
+            "--time-limit" => options.time_limit = Some(non_negative(&flag, &value()?)?),
+            "--noise-target" => options.noise_target = Some(non_negative(&flag, &value()?)?),
+            "--preview" => options.preview = Some(value()?),
+            "--preview-every" => options.preview_every = Some(non_negative(&flag, &value()?)?),
+            "--checkpoint" => options.checkpoint = Some(value()?),
+            "--checkpoint-every" => {
+                options.checkpoint_every = Some(non_negative(&flag, &value()?)?)
+            }
+            "--sampler" => options.sampling = Some(sampling(&flag, &value()?)?),
+            "--filter" => {
+                let name = value()?;
+                options.filter = Some(
+                    Filter::from_name(&name)
+                        .ok_or_else(|| format!("invalid value '{}' for {}", name, flag))?,
+                )
+            }
+            "--filter-radius" => {
+                options.filter_radius = match number(&flag, &value()?)? {
+                    r if r > 0.0 => Some(r),
+                    _ => return Err(format!("{} must be positive", flag)),
+                }
+            }
+            "--max-depth" => options.max_depth = Some(positive(&flag, &value()?)?),
+            "--russian-roulette" => {
+                options.russian_roulette = Some(match value()?.as_str() {
+                    "off" => RussianRoulette::OFF,
+                    depth => RussianRoulette::ON {
+                        min_depth: number(&flag, depth)?,
+                    },
+                })
+            }
+            "--look-from" => options.look_from = Some(vector(&flag, &value()?)?),
+            "--look-at" => options.look_at = Some(vector(&flag, &value()?)?),
+            "--fov" => options.field_of_view = Some(field_of_view(&flag, &value()?)?),
+            "--aperture" => options.aperture = Some(number(&flag, &value()?)?),
+            "--focus-distance" => options.focus_distance = Some(number(&flag, &value()?)?),
+            "--seed" => options.seed = Some(number(&flag, &value()?)?),
+            "-j" | "--threads" => options.threads = Some(number(&flag, &value()?)?),
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
 
-    let writer = PPMWriter::new("./assets/default.ppm");
-    writer.write(result).expect("Failed writing file");
+    Ok(Command::Render(Box::new(options)))
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+fn positive(flag: &str, value: &str) -> Result<i32, String> {
+    match number(flag, value)? {
+        n if n > 0 => Ok(n),
+        _ => Err(format!("{} must be positive", flag)),
+    }
+}
+
+// the viewport is spanned from the first to the last pixel, so it needs two of them
+fn dimension(flag: &str, value: &str) -> Result<i32, String> {
+    match number(flag, value)? {
+        n if n >= 2 => Ok(n),
+        _ => Err(format!("{} must be at least 2", flag)),
+    }
+}
+
+// the viewport is as high as twice the tangent of half the angle
+fn field_of_view(flag: &str, value: &str) -> Result<f64, String> {
+    match number(flag, value)? {
+        n if n > 0.0 && n < 180.0 => Ok(n),
+        _ => Err(format!("{} must be between 0 and 180 degrees", flag)),
+    }
+}
+
+fn non_negative(flag: &str, value: &str) -> Result<f64, String> {
+    let n: f64 = number(flag, value)?;
+    if n >= 0.0 && n.is_finite() {
+        Ok(n)
+    } else {
+        Err(format!("{} must not be negative", flag))
+    }
+}
+
+fn sampling(flag: &str, value: &str) -> Result<Sampling, String> {
+    match value {
+        "random" => Ok(Sampling::RANDOM),
+        "stratified" => Ok(Sampling::STRATIFIED),
+        "halton" => Ok(Sampling::HALTON),
+        "sobol" => Ok(Sampling::SOBOL),
+        "cmj" => Ok(Sampling::CMJ),
+        _ => Err(format!("invalid value '{}' for {}", value, flag)),
+    }
+}
+
+fn vector(flag: &str, value: &str) -> Result<Point3, String> {
+    let parts = value
+        .split(',')
+        .map(|part| number(flag, part))
+        .collect::<Result<Vec<f64>, String>>()?;
+    match parts[..] {
+        [x, y, z] => Ok(Point3::from(x, y, z)),
+        _ => Err(format!("{} expects three comma separated numbers", flag)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let command = parse(&[
+            "--width",
+            "64",
+            "-H",
+            "32",
+            "-s",
+            "4",
+            "--adaptive=0.02",
+            "--heatmap",
+            "heat.png",
+            "--sampler=halton",
+            "--filter",
+            "mitchell",
+            "--filter-radius=1.5",
+            "--look-from=1,2.5,-3",
+            "--fov",
+            "35",
+            "--seed",
+            "7",
+            "--russian-roulette",
+            "off",
+            "-o",
+            "out.png",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            command,
+            Command::Render(Box::new(Options {
+                width: Some(64),
+                height: Some(32),
+                samples: Some(4),
+                adaptive: Some(0.02),
+                heatmap: Some("heat.png".to_string()),
+                sampling: Some(Sampling::HALTON),
+                filter: Filter::from_name("mitchell"),
+                filter_radius: Some(1.5),
+                look_from: Some(Point3::from(1.0, 2.5, -3.0)),
+                field_of_view: Some(35.0),
+                seed: Some(7),
+                russian_roulette: Some(RussianRoulette::OFF),
+                output: Some("out.png".to_string()),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(parse(&["-s", "1", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(&["--bogus"]),
+            Err("unknown argument '--bogus'".to_string())
+        );
+        assert_eq!(
+            parse(&["--width"]),
+            Err("--width needs a value".to_string())
+        );
+        assert_eq!(
+            parse(&["--width", "1"]),
+            Err("--width must be at least 2".to_string())
+        );
+        assert_eq!(
+            parse(&["-H", "1"]),
+            Err("-H must be at least 2".to_string())
+        );
+        for fov in ["0", "180", "-30", "NaN"].iter() {
+            assert_eq!(
+                parse(&["--fov", fov]),
+                Err("--fov must be between 0 and 180 degrees".to_string())
+            );
+        }
+        assert_eq!(
+            parse(&["--samples", "0"]),
+            Err("--samples must be positive".to_string())
+        );
+        assert_eq!(
+            parse(&["--adaptive", "-1"]),
+            Err("--adaptive must be positive".to_string())
+        );
+        assert_eq!(
+            parse(&["--look-at", "1,2"]),
+            Err("--look-at expects three comma separated numbers".to_string())
+        );
+        assert_eq!(
+            parse(&["--sampler", "owen"]),
+            Err("invalid value 'owen' for --sampler".to_string())
+        );
+        assert_eq!(
+            parse(&["--filter", "sinc"]),
+            Err("invalid value 'sinc' for --filter".to_string())
+        );
+        assert_eq!(
+            parse(&["--seed", "x"]),
+            Err("invalid value 'x' for --seed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_defocus_blur() {
+        let mut settings = default_settings();
+        let options = Options {
+            look_from: Some(Point3::from(0.0, 0.0, 4.0)),
+            look_at: Some(Point3::new()),
+            aperture: Some(0.5),
+            ..Default::default()
+        };
+        apply(&options, &mut settings).unwrap();
+        assert!(matches!(
+            settings.rendering.blur,
+            DefocusBlur::ON { aperture, focus_distance } if aperture == 0.5 && focus_distance == 4.0
+        ));
+
+        let options = Options {
+            focus_distance: Some(2.5),
+            ..Default::default()
+        };
+        apply(&options, &mut settings).unwrap();
+        assert!(matches!(
+            settings.rendering.blur,
+            DefocusBlur::ON { aperture, focus_distance } if aperture == 0.5 && focus_distance == 2.5
+        ));
+        apply(&Options::default(), &mut settings).unwrap();
+        assert!(matches!(
+            settings.rendering.blur,
+            DefocusBlur::ON { focus_distance, .. } if focus_distance == 2.5
+        ));
+
+        let options = Options {
+            aperture: Some(0.0),
+            ..Default::default()
+        };
+        apply(&options, &mut settings).unwrap();
+        assert!(matches!(settings.rendering.blur, DefocusBlur::OFF));
+    }
+
+    #[test]
+    fn test_progressive() {
+        let options = match parse(&["--preview", "preview.png", "--time-limit=90"]) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(
+            options.progressive(),
+            Some(Progressive {
+                time_limit: Some(Duration::from_secs(90)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(Options::default().progressive(), None);
+        let options = match parse(&[
+            "--checkpoint",
+            "render.checkpoint",
+            "--checkpoint-every=600",
+        ]) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(
+            options.progressive(),
+            Some(Progressive {
+                checkpoint: Some("render.checkpoint".to_string()),
+                checkpoint_interval: Some(Duration::from_secs(600)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(&["--noise-target", "-0.1"]),
+            Err("--noise-target must not be negative".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_adaptive() {
+        let mut settings = default_settings();
+        let options = Options {
+            adaptive: Some(0.05),
+            ..Default::default()
+        };
+        apply(&options, &mut settings).unwrap();
+        assert!(matches!(
+            settings.rendering.antialiasing,
+            Antialiasing::ADAPTIVE { min_samples: 16, max_samples: 50, threshold } if threshold == 0.05
+        ));
+
+        let options = Options {
+            samples: Some(8),
+            min_samples: Some(12),
+            ..Default::default()
+        };
+        apply(&options, &mut settings).unwrap();
+        assert!(matches!(
+            settings.rendering.antialiasing,
+            Antialiasing::ADAPTIVE {
+                min_samples: 8,
+                max_samples: 8,
+                ..
+            }
+        ));
+
+        let mut settings = default_settings();
+        let options = Options {
+            min_samples: Some(12),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply(&options, &mut settings),
+            Err("--min-samples requires --adaptive or an adaptive scene".to_string())
+        );
+    }
 }
@@ -0,0 +1,146 @@
+use crate::math::{Point3, Ray, Vec3};
+
+/// axis-aligned bounding box spanned by its minimum and maximum corner
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+	min: Point3,
+	max: Point3,
+}
+
+impl Aabb {
+	/// box that contains nothing, the identity for `surrounding`
+	pub fn empty() -> Self {
+		Self {
+			min: Point3::from(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+			max: Point3::from(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+		}
+	}
+
+	pub fn from(a: Point3, b: Point3) -> Self {
+		Self {
+			min: Point3::from(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+			max: Point3::from(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+		}
+	}
+
+	pub fn min(&self) -> &Point3 {
+		&self.min
+	}
+
+	pub fn max(&self) -> &Point3 {
+		&self.max
+	}
+
+	pub fn surrounding(&self, other: &Aabb) -> Aabb {
+		Self {
+			min: Point3::from(
+				self.min.x().min(other.min.x()),
+				self.min.y().min(other.min.y()),
+				self.min.z().min(other.min.z()),
+			),
+			max: Point3::from(
+				self.max.x().max(other.max.x()),
+				self.max.y().max(other.max.y()),
+				self.max.z().max(other.max.z()),
+			),
+		}
+	}
+
+	pub fn including(&self, p: &Point3) -> Aabb {
+		self.surrounding(&Aabb { min: *p, max: *p })
+	}
+
+	pub fn centroid(&self) -> Point3 {
+		(self.min + self.max) * 0.5
+	}
+
+	pub fn extent(&self) -> Vec3 {
+		self.max - self.min
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+	}
+
+	pub fn surface_area(&self) -> f64 {
+		if self.is_empty() {
+			return 0.0;
+		}
+		let d = self.extent();
+		2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+	}
+
+	/// index of the axis along which the box is the widest
+	pub fn longest_axis(&self) -> usize {
+		let d = self.extent();
+		if d.x() > d.y() && d.x() > d.z() {
+			0
+		} else if d.y() > d.z() {
+			1
+		} else {
+			2
+		}
+	}
+
+	/// slab test, yields whether the ray passes through the box within [t_min, t_max]
+	pub fn hit(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+		for axis in 0..3 {
+			let inv_d = 1.0 / r.direction()[axis];
+			let mut t0 = (self.min[axis] - r.origin()[axis]) * inv_d;
+			let mut t1 = (self.max[axis] - r.origin()[axis]) * inv_d;
+			if inv_d < 0.0 {
+				std::mem::swap(&mut t0, &mut t1);
+			}
+			// written so that NaNs from 0 * inf keep the current interval
+			t_min = if t0 > t_min { t0 } else { t_min };
+			t_max = if t1 < t_max { t1 } else { t_max };
+			if t_max < t_min {
+				return false;
+			}
+		}
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_hit() {
+		let b = Aabb::from(Point3::from(-1.0, -1.0, -1.0), Point3::from(1.0, 1.0, 1.0));
+		let r = Ray::from(&Point3::from(0.0, 0.0, 5.0), &Vec3::from(0.0, 0.0, -1.0));
+		assert!(b.hit(&r, 0.0, 100.0));
+		assert!(!b.hit(&r, 0.0, 3.0));
+
+		let miss = Ray::from(&Point3::from(2.0, 0.0, 5.0), &Vec3::from(0.0, 0.0, -1.0));
+		assert!(!b.hit(&miss, 0.0, 100.0));
+	}
+
+	#[test]
+	fn test_hit_flat_box() {
+		// a box without thickness along the ray's zero direction components
+		let b = Aabb::from(Point3::from(-1.0, 0.0, -1.0), Point3::from(1.0, 0.0, 1.0));
+		let r = Ray::from(&Point3::from(0.0, 1.0, 0.0), &Vec3::from(0.0, -1.0, 0.0));
+		assert!(b.hit(&r, 0.0, 10.0));
+	}
+
+	#[test]
+	fn test_surrounding() {
+		let a = Aabb::from(Point3::from(0.0, 0.0, 0.0), Point3::from(1.0, 1.0, 1.0));
+		let b = Aabb::from(Point3::from(-1.0, 0.5, 0.0), Point3::from(0.5, 2.0, 1.0));
+		let s = a.surrounding(&b);
+		assert_eq!(s.min(), &Point3::from(-1.0, 0.0, 0.0));
+		assert_eq!(s.max(), &Point3::from(1.0, 2.0, 1.0));
+		assert_eq!(Aabb::empty().surrounding(&a), a);
+	}
+
+	#[test]
+	fn test_surface_area_and_axis() {
+		let b = Aabb::from(Point3::from(0.0, 0.0, 0.0), Point3::from(1.0, 2.0, 3.0));
+		assert_eq!(b.surface_area(), 22.0);
+		assert_eq!(b.longest_axis(), 2);
+		assert_eq!(Aabb::empty().surface_area(), 0.0);
+	}
+}
@@ -1,11 +1,15 @@
 #![allow(dead_code)]
+pub use aabb::Aabb;
 pub use ray::Ray;
 pub use std::f64::consts::PI;
-pub use std::f64::INFINITY;
-pub use vec3::{cross, dot, norm, reflect, refract, Point3, Vec3};
+pub const INFINITY: f64 = f64::INFINITY;
+pub use vec3::{cross, dot, norm, orthonormal_basis, reflect, refract, Point3, Vec3};
 
-use rand_distr::{Distribution, Uniform};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use std::cell::RefCell;
 
+pub mod aabb;
 pub mod ray;
 pub mod vec3;
 
@@ -13,18 +17,45 @@ pub fn radians(degrees: f64) -> f64 {
 	degrees * PI / 180.0
 }
 
-pub fn rand() -> f64 {
-	let mut rng = rand::thread_rng();
-	let uniform_dst = Uniform::new_inclusive(0.0, 1.0);
+thread_local! {
+	// every thread starts from entropy, the renderer reseeds it for each sample
+	static RNG: RefCell<Pcg64Mcg> = RefCell::new(Pcg64Mcg::from_entropy());
+}
+
+/// restarts the random sequence of the current thread, `rand` and `rand_rng`
+/// yield the same numbers after reseeding with the same seed
+pub fn reseed(seed: u64) {
+	RNG.with(|rng| *rng.borrow_mut() = Pcg64Mcg::seed_from_u64(seed));
+}
+
+/// combines a render seed with a pixel and sample index into the seed of one sample
+pub fn sample_seed(seed: u64, pixel: u64, sample: u64) -> u64 {
+	splitmix64(splitmix64(splitmix64(seed) ^ pixel) ^ sample)
+}
 
-	uniform_dst.sample(&mut rng)
+fn splitmix64(x: u64) -> u64 {
+	let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+pub fn rand() -> f64 {
+	rand_rng(0.0, 1.0)
 }
 
 pub fn rand_rng(min: f64, max: f64) -> f64 {
-	let mut rng = rand::thread_rng();
-	let uniform_dst = Uniform::new_inclusive(min, max);
+	RNG.with(|rng| rng.borrow_mut().gen_range(min..=max))
+}
 
-	uniform_dst.sample(&mut rng)
+/// multiple importance sampling weight of a sample drawn with density `pdf` when
+/// another strategy could have drawn it with density `other`
+pub fn power_heuristic(pdf: f64, other: f64) -> f64 {
+	let (a, b) = (pdf * pdf, other * other);
+	if a + b == 0.0 {
+		return 0.0;
+	}
+	a / (a + b)
 }
 
 #[inline]
@@ -44,6 +75,59 @@ mod test {
 
 	use super::*;
 
+	#[test]
+	fn test_reseed_repeats_sequence() {
+		reseed(42);
+		let first: Vec<f64> = (0..8).map(|_| rand()).collect();
+		reseed(42);
+		let second: Vec<f64> = (0..8).map(|_| rand()).collect();
+		assert_eq!(first, second);
+		assert!(first.iter().all(|v| (0.0..=1.0).contains(v)));
+
+		reseed(43);
+		assert_ne!(first[0], rand());
+	}
+
+	#[test]
+	fn test_sample_seeds_differ() {
+		let seeds = [
+			sample_seed(0, 0, 0),
+			sample_seed(0, 0, 1),
+			sample_seed(0, 1, 0),
+			sample_seed(1, 0, 0),
+		];
+		for i in 0..seeds.len() {
+			for j in i + 1..seeds.len() {
+				assert_ne!(seeds[i], seeds[j]);
+			}
+		}
+	}
+
+	#[test]
+	fn test_power_heuristic() {
+		assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+		assert_eq!(power_heuristic(0.0, 1.0), 0.0);
+		assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+		assert_eq!(power_heuristic(1.0, 3.0), 0.1);
+		assert_eq!(power_heuristic(2.0, 2.0) + power_heuristic(2.0, 2.0), 1.0);
+	}
+
+	#[test]
+	fn test_orthonormal_basis() {
+		for n in [
+			Vec3::from(0.0, 0.0, 1.0),
+			Vec3::from(0.0, 0.0, -1.0),
+			norm(&Vec3::from(1.0, -2.0, 0.5)),
+		] {
+			let (u, v) = orthonormal_basis(&n);
+			for (a, b) in [(u, v), (u, n), (v, n)] {
+				assert!(dot(&a, &b).abs() < 1e-12);
+			}
+			assert!((u.len() - 1.0).abs() < 1e-12 && (v.len() - 1.0).abs() < 1e-12);
+			assert!((dot(&cross(&u, &v), &n) - 1.0).abs() < 1e-12);
+		}
+	}
+
 	#[test]
 	fn test_degress_to_radians() {
 		let degrees = 90.0;
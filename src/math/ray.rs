@@ -6,6 +6,12 @@ pub struct Ray {
 	dir: Vec3,
 }
 
+impl Default for Ray {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Ray {
 	pub fn new() -> Self {
 		Self {
@@ -16,8 +22,8 @@ impl Ray {
 
 	pub fn from(orig: &Point3, dir: &Vec3) -> Self {
 		Self {
-			orig: orig.clone(),
-			dir: dir.clone(),
+			orig: *orig,
+			dir: *dir,
 		}
 	}
 
@@ -30,7 +36,7 @@ impl Ray {
 	}
 
 	pub fn at(&self, t: f64) -> Point3 {
-		&self.orig + &(&self.dir * t)
+		self.orig + (self.dir * t)
 	}
 }
 
@@ -40,6 +40,18 @@ pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
 	r_out_perp + r_out_parallel
 }
 
+/// two unit vectors that form a right handed orthonormal basis with the unit vector `n`
+pub fn orthonormal_basis(n: &Vec3) -> (Vec3, Vec3) {
+	// Duff et al., Building an Orthonormal Basis, Revisited
+	let sign = 1.0f64.copysign(n.z());
+	let a = -1.0 / (sign + n.z());
+	let b = n.x() * n.y() * a;
+	(
+		Vec3::from(1.0 + sign * n.x() * n.x() * a, sign * b, -sign * n.x()),
+		Vec3::from(b, sign + n.y() * n.y() * a, -n.y()),
+	)
+}
+
 pub type Point3 = Vec3;
 
 impl Vec3 {
@@ -65,7 +77,7 @@ impl Vec3 {
 
 	pub fn near_zero(&self) -> bool {
 		let s = 1e-8;
-		self.e.0.abs() < s && self.e.1.abs() < s && self.e.2 < s
+		self.e.0.abs() < s && self.e.1.abs() < s && self.e.2.abs() < s
 	}
 
 	pub fn random_in_unit_sphere() -> Self {
@@ -91,6 +103,42 @@ impl Vec3 {
 		}
 	}
 
+	/// maps a point of the unit square to a unit vector in the hemisphere around the
+	/// unit vector `normal`, uniform points give a density of the cosine to the normal over pi
+	pub fn cosine_direction(normal: &Self, (s, t): (f64, f64)) -> Self {
+		let (u, v) = orthonormal_basis(normal);
+		let phi = 2.0 * std::f64::consts::PI * s;
+		let r = t.sqrt();
+		r * phi.cos() * u + r * phi.sin() * v + (1.0 - t).sqrt() * normal
+	}
+
+	/// maps a point of the unit square to the unit disk in the xy plane keeping
+	/// neighbouring points close, Shirley and Chiu's concentric mapping
+	pub fn concentric_disk((s, t): (f64, f64)) -> Self {
+		let (a, b) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+		if a == 0.0 && b == 0.0 {
+			return Vec3::new();
+		}
+		let quarter = std::f64::consts::FRAC_PI_4;
+		let (r, theta) = if a.abs() > b.abs() {
+			(a, quarter * (b / a))
+		} else {
+			(b, 2.0 * quarter - quarter * (a / b))
+		};
+		Vec3::from(r * theta.cos(), r * theta.sin(), 0.0)
+	}
+
+	/// maps a point of the unit square to a unit vector in the hemisphere around the
+	/// unit vector `axis`, uniform points give a density of the cosine to the axis to the
+	/// power of `exponent` times (exponent + 1) / (2 pi)
+	pub fn phong_direction(axis: &Self, exponent: f64, (s, t): (f64, f64)) -> Self {
+		let (u, v) = orthonormal_basis(axis);
+		let phi = 2.0 * std::f64::consts::PI * t;
+		let cosine = s.powf(1.0 / (exponent + 1.0));
+		let sine = (1.0 - cosine * cosine).max(0.0).sqrt();
+		sine * phi.cos() * u + sine * phi.sin() * v + cosine * axis
+	}
+
 	pub fn random_in_unit_disk() -> Self {
 		loop {
 			let p = Vec3::from(rand_rng(-1.0, 1.0), rand_rng(-1.0, 1.0), 0.0);
@@ -297,7 +345,7 @@ impl ops::IndexMut<usize> for Vec3 {
 impl ops::MulAssign for Vec3 {
 	fn mul_assign(&mut self, rhs: Self) {
 		*self = Self {
-			e: (self.e.0 * rhs.e.0, self.e.1 * rhs.e.1, self.e.2 * self.e.2),
+			e: (self.e.0 * rhs.e.0, self.e.1 * rhs.e.1, self.e.2 * rhs.e.2),
 		}
 	}
 }
@@ -317,7 +365,7 @@ impl ops::Div for Vec3 {
 
 	fn div(self, rhs: Vec3) -> Self::Output {
 		Self {
-			e: (self.e.0 / rhs.e.0, self.e.1 / rhs.e.1, self.e.2 / self.e.2),
+			e: (self.e.0 / rhs.e.0, self.e.1 / rhs.e.1, self.e.2 / rhs.e.2),
 		}
 	}
 }
@@ -325,7 +373,7 @@ impl ops::Div for Vec3 {
 impl ops::DivAssign for Vec3 {
 	fn div_assign(&mut self, rhs: Self) {
 		*self = Self {
-			e: (self.e.0 / rhs.e.0, self.e.1 / rhs.e.1, self.e.2 / self.e.2),
+			e: (self.e.0 / rhs.e.0, self.e.1 / rhs.e.1, self.e.2 / rhs.e.2),
 		}
 	}
 }
@@ -393,7 +441,7 @@ mod test {
 
 	#[test]
 	fn test_add_two_vec3() {
-		let res = &Vec3::from(1.0, 2.0, 3.0) + &Vec3::from(3.0, 2.0, 1.0);
+		let res = Vec3::from(1.0, 2.0, 3.0) + Vec3::from(3.0, 2.0, 1.0);
 		assert_eq!(res, Vec3::from(4.0, 4.0, 4.0));
 	}
 
@@ -434,8 +482,8 @@ mod test {
 	#[test]
 	fn test_mul_assign() {
 		let mut v = Vec3::from(1.0, 2.0, 3.0);
-		v *= Vec3::from(1.0, 2.0, 3.0);
-		assert_eq!(v, Vec3::from(1.0, 4.0, 9.0));
+		v *= Vec3::from(2.0, 3.0, 4.0);
+		assert_eq!(v, Vec3::from(2.0, 6.0, 12.0));
 	}
 
 	#[test]
@@ -446,15 +494,22 @@ mod test {
 
 	#[test]
 	fn test_div() {
-		let res = Vec3::from(1.0, 2.0, 3.0) / Vec3::from(1.0, 2.0, 3.0);
-		assert_eq!(res, Vec3::from(1.0, 1.0, 1.0));
+		let res = Vec3::from(1.0, 2.0, 3.0) / Vec3::from(2.0, 4.0, 6.0);
+		assert_eq!(res, Vec3::from(0.5, 0.5, 0.5));
 	}
 
 	#[test]
 	fn test_div_assign() {
 		let mut v = Vec3::from(1.0, 2.0, 3.0);
-		v /= Vec3::from(1.0, 4.0, 3.0);
-		assert_eq!(v, Vec3::from(1.0, 0.5, 1.0));
+		v /= Vec3::from(1.0, 4.0, 6.0);
+		assert_eq!(v, Vec3::from(1.0, 0.5, 0.5));
+	}
+
+	#[test]
+	fn test_near_zero() {
+		assert!(Vec3::from(1e-9, -1e-9, 1e-9).near_zero());
+		assert!(!Vec3::from(0.0, 0.0, -1.0).near_zero());
+		assert!(!Vec3::from(0.0, -1.0, 0.0).near_zero());
 	}
 
 	#[test]
@@ -502,7 +557,7 @@ mod test {
 	fn test_norm() {
 		let v = Vec3::from(-2.0, 4.0, 5.0);
 		let unit_vec = norm(&v);
-		assert_eq!(unit_vec, &v / (45.0 as f64).sqrt());
+		assert_eq!(unit_vec, v / 45.0_f64.sqrt());
 		assert_eq!(unit_vec.len(), 1.0);
 	}
 
@@ -547,8 +602,23 @@ mod test {
 	#[test]
 	fn test_iter_sum() {
 		let v = Vec3::from(1.0, 1.0, 1.0);
-		let vs = vec![v, v, v];
+		let vs = [v, v, v];
 
 		assert_eq!(Vec3::from(3.0, 3.0, 3.0), vs.iter().sum());
 	}
+
+	#[test]
+	fn test_phong_direction() {
+		let axis = norm(&Vec3::from(1.0, 2.0, -2.0));
+		assert_eq!(
+			Vec3::phong_direction(&axis, 10.0, (1.0, 0.3)).round_to(1_000_000),
+			axis.round_to(1_000_000)
+		);
+		for i in 0..=10 {
+			let d = Vec3::phong_direction(&axis, 10.0, (i as f64 / 10.0, 0.37));
+			assert!((d.len() - 1.0).abs() < 1e-9);
+			let cosine = (i as f64 / 10.0).powf(1.0 / 11.0);
+			assert!((dot(&d, &axis) - cosine).abs() < 1e-9);
+		}
+	}
 }
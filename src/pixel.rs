@@ -1,3 +1,4 @@
+use crate::color::srgb_encode;
 use crate::math::clamp;
 use crate::Color;
 
@@ -8,6 +9,12 @@ pub struct Pixel {
 	b: i32,
 }
 
+impl Default for Pixel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Pixel {
 	pub fn from(r: i32, g: i32, b: i32) -> Self {
 		Self { r, g, b }
@@ -29,18 +36,42 @@ impl Pixel {
 
 impl Pixel {
 	pub fn from_color(color: Color) -> Pixel {
+		Pixel::from_color_with_max(color, 255)
+	}
+
+	/// sRGB encodes and quantizes the color to channel values in `0..=max_color`
+	pub fn from_color_with_max(color: Color, max_color: i32) -> Pixel {
 		let Color { e: (r, g, b) } = Pixel::gamma_correct(color);
+		let quantize =
+			|c: f64| (((max_color + 1) as f64 * clamp(c, 0.0, 1.0)) as i32).min(max_color);
 
-		Pixel::from(
-			(256.0 * clamp(r, 0.0, 0.999)) as i32,
-			(256.0 * clamp(g, 0.0, 0.999)) as i32,
-			(256.0 * clamp(b, 0.0, 0.999)) as i32,
-		)
+		Pixel::from(quantize(r), quantize(g), quantize(b))
 	}
 
 	fn gamma_correct(color: Color) -> Color {
+		let encode = |c: f64| srgb_encode(clamp(c, 0.0, 1.0));
 		Color {
-			e: (color.x().sqrt(), color.y().sqrt(), color.z().sqrt()),
+			e: (encode(color.x()), encode(color.y()), encode(color.z())),
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_from_color() {
+		let p = Pixel::from_color(Color::from(0.0, 0.25, 1.0));
+		assert_eq!((p.r(), p.g(), p.b()), (0, 137, 255));
+	}
+
+	#[test]
+	fn test_from_color_with_max() {
+		let p = Pixel::from_color_with_max(Color::from(0.0, 0.25, 2.0), 65535);
+		assert_eq!((p.r(), p.g(), p.b()), (0, 35199, 65535));
+
+		let p = Pixel::from_color_with_max(Color::from(0.0, 0.25, 1.0), 1);
+		assert_eq!((p.r(), p.g(), p.b()), (0, 1, 1));
+	}
+}
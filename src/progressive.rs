@@ -0,0 +1,334 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::checkpoint::Checkpoint;
+use crate::filter::Film;
+use crate::settings::{Antialiasing, Progressive};
+use crate::utils::{ProgressBar, Welford};
+use crate::{relative_error, Renderer, RenderingResult, WriteResult};
+
+impl Renderer {
+	/// renders one sample of every pixel per pass into an accumulation buffer until every
+	/// pixel has its samples or a limit of `progressive` is reached, writing the image so
+	/// far with `preview` between passes; adaptive antialiasing leaves pixels out of later
+	/// passes once they converged
+	pub fn render_progressive(
+		&self,
+		progressive: &Progressive,
+		preview: Option<&dyn WriteResult>,
+	) -> Result<RenderingResult, Box<dyn Error>> {
+		let state = Checkpoint::new(&self.settings, &progressive.scene);
+		self.resume_progressive(state, progressive, preview)
+	}
+
+	/// continues a progressive render from a checkpoint of a render of the same scene with
+	/// the same settings, the result is the one the render would have had without the
+	/// interruption
+	pub fn resume_progressive(
+		&self,
+		mut state: Checkpoint,
+		progressive: &Progressive,
+		preview: Option<&dyn WriteResult>,
+	) -> Result<RenderingResult, Box<dyn Error>> {
+		if !state.matches(&self.settings, &progressive.scene) {
+			return Err("the checkpoint was made of another scene or with other settings".into());
+		}
+		let start = Instant::now();
+		let (min_samples, max_samples, threshold) = match *self.settings.antialiasing() {
+			Antialiasing::MSAA { samples_per_pixel } => (samples_per_pixel, samples_per_pixel, 0.0),
+			Antialiasing::ADAPTIVE {
+				min_samples,
+				max_samples,
+				threshold,
+			} => (min_samples, max_samples.max(1), threshold),
+			Antialiasing::NONE => (1, 1, 0.0),
+		};
+		let complete = |state: &Checkpoint| {
+			state.passes >= max_samples
+				|| !state.active.contains(&true)
+				|| progressive
+					.noise_target
+					.is_some_and(|target| mean_error(&state.pixels) <= target)
+		};
+		let width = self.settings.width() as usize;
+		let pool = self.thread_pool();
+		let progress = ProgressBar::from(max_samples);
+		let mut last_preview: Option<Instant> = None;
+		let mut last_checkpoint = Some(start);
+		let mut finished = complete(&state);
+
+		while !finished {
+			let mut luminances = Vec::with_capacity(state.pixels.len());
+			// borrowed field by field, the closures would capture all of `state` otherwise
+			let (pass, film, active) = (state.passes, &mut state.film, &state.active);
+			pool.install(|| {
+				self.render_rows(
+					|j| {
+						let row = j as usize * width..(j as usize + 1) * width;
+						self.render_pass_row(pass, j, &active[row])
+					},
+					|(row, row_luminances)| {
+						film.add(&row);
+						luminances.extend(row_luminances);
+					},
+				)
+			});
+			let pixels = state.pixels.iter_mut().zip(&mut state.active);
+			for ((pixel, active), luminance) in pixels.zip(luminances) {
+				if let Some(luminance) = luminance {
+					pixel.add(luminance);
+					if pixel.count() as i32 >= min_samples && relative_error(pixel) <= threshold {
+						*active = false;
+					}
+				}
+			}
+			state.passes += 1;
+			progress.inc();
+			finished = complete(&state)
+				|| progressive
+					.time_limit
+					.is_some_and(|limit| start.elapsed() >= limit);
+
+			if let Some(preview) = preview {
+				if finished || due(progressive.preview_interval, last_preview) {
+					preview.write(self.result(state.film.colors(), vec![]))?;
+					last_preview = Some(Instant::now());
+				}
+			}
+			if let Some(path) = &progressive.checkpoint {
+				if finished || due(progressive.checkpoint_interval, last_checkpoint) {
+					state.save(path)?;
+					last_checkpoint = Some(Instant::now());
+				}
+			}
+		}
+
+		let samples: Vec<i32> = state.pixels.iter().map(|p| p.count() as i32).collect();
+		let layers = self.layers(&pool, &samples);
+		let mut result = self.result(state.film.colors(), layers);
+		result
+			.metadata
+			.push(("Passes".to_string(), state.passes.to_string()));
+		Ok(result)
+	}
+
+	/// splats sample `pass` of the pixels of row `curr_height` that are still `active`,
+	/// together with the luminance of every sample taken
+	fn render_pass_row(
+		&self,
+		pass: i32,
+		curr_height: i32,
+		active: &[bool],
+	) -> (Film, Vec<Option<f64>>) {
+		let mut film = self.row_film(curr_height);
+		let luminances = active
+			.iter()
+			.enumerate()
+			.map(|(i, &active)| {
+				if active {
+					Some(
+						self.splat_sample(&mut film, i as i32, curr_height, pass)
+							.luminance(),
+					)
+				} else {
+					None
+				}
+			})
+			.collect();
+		(film, luminances)
+	}
+}
+
+/// whether `interval` has passed since `last`, always without an interval or a last time
+fn due(interval: Option<Duration>, last: Option<Instant>) -> bool {
+	match (interval, last) {
+		(Some(interval), Some(last)) => last.elapsed() >= interval,
+		_ => true,
+	}
+}
+
+/// mean relative error of all pixels, infinite until every pixel has two samples
+fn mean_error(pixels: &[Welford]) -> f64 {
+	pixels.iter().map(relative_error).sum::<f64>() / pixels.len() as f64
+}
+
+#[cfg(test)]
+mod test {
+	use std::cell::RefCell;
+
+	use super::*;
+	use crate::settings::{Aov, Background, ImageSettings, RenderSettings};
+	use crate::{Color, Scene, Settings};
+
+	#[derive(Default)]
+	struct Previews {
+		colors: RefCell<Vec<Vec<Color>>>,
+	}
+
+	impl WriteResult for Previews {
+		fn write(&self, result: RenderingResult) -> Result<(), Box<dyn Error>> {
+			self.colors.borrow_mut().push(result.colors);
+			Ok(())
+		}
+	}
+
+	fn renderer(antialiasing: Antialiasing, background: Background) -> Renderer {
+		let settings = Settings {
+			image: ImageSettings {
+				width: 12,
+				height: 8,
+			},
+			rendering: RenderSettings {
+				antialiasing,
+				background,
+				aovs: vec![Aov::SAMPLES],
+				..Default::default()
+			},
+			..Default::default()
+		};
+		Renderer::from(Scene::random(0), settings)
+	}
+
+	fn passes(result: &RenderingResult) -> &str {
+		&result
+			.metadata
+			.iter()
+			.find(|(k, _)| k == "Passes")
+			.unwrap()
+			.1
+	}
+
+	#[test]
+	fn test_passes_add_up_to_the_full_render() {
+		let renderer = renderer(
+			Antialiasing::MSAA {
+				samples_per_pixel: 3,
+			},
+			Background::SKY,
+		);
+		let previews = Previews::default();
+		let result = renderer
+			.render_progressive(&Progressive::default(), Some(&previews))
+			.unwrap();
+
+		assert_eq!(result.colors, renderer.render().colors);
+		assert_eq!(passes(&result), "3");
+		assert_eq!(result.layers[0].colors[0], Color::from(3.0, 3.0, 3.0));
+		let previews = previews.colors.into_inner();
+		assert_eq!(previews.len(), 3);
+		assert_ne!(previews[0], previews[1]);
+		assert_eq!(previews[2], result.colors);
+	}
+
+	#[test]
+	fn test_resumed_render_matches_an_uninterrupted_one() {
+		let adaptive = renderer(
+			Antialiasing::ADAPTIVE {
+				min_samples: 2,
+				max_samples: 6,
+				threshold: 0.05,
+			},
+			Background::SKY,
+		);
+		let path = std::env::temp_dir().join(format!(
+			"raytracing_weekend_resume_{}.checkpoint",
+			std::process::id()
+		));
+		let path = path.to_str().unwrap().to_string();
+		let interrupted = Progressive {
+			time_limit: Some(Duration::ZERO),
+			checkpoint: Some(path.clone()),
+			..Default::default()
+		};
+		adaptive.render_progressive(&interrupted, None).unwrap();
+		let checkpoint = Checkpoint::load(&path).unwrap();
+		assert_eq!(checkpoint.passes(), 1);
+
+		let resumed = adaptive
+			.resume_progressive(checkpoint, &Progressive::default(), None)
+			.unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let uninterrupted = adaptive
+			.render_progressive(&Progressive::default(), None)
+			.unwrap();
+		assert_eq!(resumed.colors, uninterrupted.colors);
+		assert_eq!(resumed.layers[0].colors, uninterrupted.layers[0].colors);
+		assert_eq!(passes(&resumed), passes(&uninterrupted));
+
+		let other = renderer(
+			Antialiasing::MSAA {
+				samples_per_pixel: 6,
+			},
+			Background::SKY,
+		);
+		let checkpoint = Checkpoint::new(&adaptive.settings, "");
+		assert!(other
+			.resume_progressive(checkpoint, &Progressive::default(), None)
+			.is_err());
+		let other_scene = Progressive {
+			scene: "other".to_string(),
+			..Default::default()
+		};
+		let checkpoint = Checkpoint::new(&adaptive.settings, "");
+		assert!(adaptive
+			.resume_progressive(checkpoint, &other_scene, None)
+			.is_err());
+	}
+
+	#[test]
+	fn test_limits_end_the_render_early() {
+		let renderer = renderer(
+			Antialiasing::MSAA {
+				samples_per_pixel: 100,
+			},
+			Background::SOLID {
+				color: Color::from(0.5, 0.5, 0.5),
+			},
+		);
+		let out_of_time = Progressive {
+			time_limit: Some(Duration::ZERO),
+			..Default::default()
+		};
+		let result = renderer.render_progressive(&out_of_time, None).unwrap();
+		assert_eq!(passes(&result), "1");
+
+		let converged = Progressive {
+			noise_target: Some(0.1),
+			..Default::default()
+		};
+		let result = renderer.render_progressive(&converged, None).unwrap();
+		let passes: i32 = passes(&result).parse().unwrap();
+		assert!(passes > 1 && passes < 100, "{} passes", passes);
+	}
+
+	#[test]
+	fn test_adaptive_passes_skip_converged_pixels() {
+		// a uniform background converges after the minimum number of samples
+		let renderer = renderer(
+			Antialiasing::ADAPTIVE {
+				min_samples: 4,
+				max_samples: 32,
+				threshold: 0.01,
+			},
+			Background::SOLID {
+				color: Color::from(0.5, 0.5, 0.5),
+			},
+		);
+		let previews = Previews::default();
+		let result = renderer
+			.render_progressive(
+				&Progressive {
+					preview_interval: Some(Duration::from_secs(3600)),
+					..Default::default()
+				},
+				Some(&previews),
+			)
+			.unwrap();
+
+		let samples = &result.layers[0].colors;
+		assert!(samples.iter().all(|c| c.x() >= 4.0 && c.x() <= 32.0));
+		assert!(samples.iter().any(|c| c.x() == 4.0));
+		// the first and the last pass write a preview, the interval skips the others
+		assert_eq!(previews.colors.borrow().len(), 2);
+	}
+}
@@ -0,0 +1,48 @@
+use super::{permute, random_float, scramble, Sampler, StratifiedSampler};
+
+/// Kensler's correlated multi-jittered sampling. The samples of a pixel stratify a
+/// grid of cells as well as every row and column of it, single dimensions are
+/// jittered strata.
+pub struct CmjSampler {
+	samples_per_pixel: u32,
+	seed: u64,
+	strata: StratifiedSampler,
+}
+
+impl CmjSampler {
+	pub fn from(samples_per_pixel: u32, seed: u64) -> Self {
+		Self {
+			samples_per_pixel,
+			seed,
+			strata: StratifiedSampler::from(samples_per_pixel, seed),
+		}
+	}
+}
+
+impl Sampler for CmjSampler {
+	fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f64 {
+		self.strata.get_1d(pixel, index, dimension)
+	}
+
+	fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> (f64, f64) {
+		let count = self.samples_per_pixel;
+		let m = (count as f64).sqrt() as u32;
+		let n = count.div_ceil(m);
+		let round = index / count as u64;
+		let p = scramble(self.seed ^ round, pixel, dimension);
+
+		let s = permute(
+			(index % count as u64) as u32,
+			m * n,
+			p.wrapping_mul(0x5163_3e2d),
+		);
+		let sx = permute(s % m, m, p.wrapping_mul(0xa511_e9b3));
+		let sy = permute(s / m, n, p.wrapping_mul(0x63d8_3595));
+		let jx = random_float(s, p.wrapping_mul(0xa399_d265));
+		let jy = random_float(s, p.wrapping_mul(0x711a_d6a5));
+		(
+			((s % m) as f64 + (sy as f64 + jx) / n as f64) / m as f64,
+			((s / m) as f64 + (sx as f64 + jy) / m as f64) / n as f64,
+		)
+	}
+}
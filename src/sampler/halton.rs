@@ -0,0 +1,72 @@
+use super::{scramble, to_unit, uniform, Sampler};
+
+const PRIMES: [u64; 32] = [
+	2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+	101, 103, 107, 109, 113, 127, 131,
+];
+
+/// The Halton sequence, radical inverses of the sample index in a prime base per
+/// dimension. Every pixel shifts the sequence by its own random offset. Dimensions
+/// past the table of primes fall back to random numbers as higher bases correlate badly.
+pub struct HaltonSampler {
+	seed: u64,
+}
+
+impl HaltonSampler {
+	pub fn from(seed: u64) -> Self {
+		Self { seed }
+	}
+
+	fn value(&self, pixel: u64, index: u64, dimension: u32) -> f64 {
+		let base = match PRIMES.get(dimension as usize) {
+			Some(base) => *base,
+			None => return uniform(),
+		};
+		// Cranley Patterson rotation
+		let offset = to_unit(scramble(self.seed, pixel, dimension));
+		let value = radical_inverse(base, index) + offset;
+		if value >= 1.0 {
+			value - 1.0
+		} else {
+			value
+		}
+	}
+}
+
+/// mirrors the digits of `index` in `base` at the radix point
+fn radical_inverse(base: u64, mut index: u64) -> f64 {
+	let inverse_base = 1.0 / base as f64;
+	let mut factor = inverse_base;
+	let mut result = 0.0;
+	while index > 0 {
+		result += (index % base) as f64 * factor;
+		index /= base;
+		factor *= inverse_base;
+	}
+	result
+}
+
+impl Sampler for HaltonSampler {
+	fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f64 {
+		self.value(pixel, index, dimension)
+	}
+
+	fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> (f64, f64) {
+		(
+			self.value(pixel, index, dimension),
+			self.value(pixel, index, dimension + 1),
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_radical_inverse() {
+		let values: Vec<f64> = (0..5).map(|i| radical_inverse(2, i)).collect();
+		assert_eq!(values, vec![0.0, 0.5, 0.25, 0.75, 0.125]);
+		assert_eq!(radical_inverse(3, 5), 2.0 / 3.0 + 1.0 / 9.0);
+	}
+}
@@ -0,0 +1,291 @@
+//! Sample points for the random decisions made while rendering a pixel.
+//!
+//! Every sample of a pixel is a point in a unit hypercube. The renderer starts a sample
+//! with `start_sample` and every random decision draws the next dimensions with
+//! `next_1d` or `next_2d`: two for the position within the pixel, two for the lens and
+//! then a few for each bounce. Samplers that spread the points of a pixel evenly over
+//! the leading dimensions give less noise than independent random numbers.
+use std::cell::RefCell;
+use std::sync::Arc;
+
+pub use cmj::CmjSampler;
+pub use halton::HaltonSampler;
+pub use random::RandomSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+use crate::math::{rand, sample_seed};
+use crate::settings::{Antialiasing, Sampling, Settings};
+
+mod cmj;
+mod halton;
+mod random;
+mod sobol;
+mod stratified;
+
+pub trait Sampler: Send + Sync {
+	/// value in `[0, 1)` of `dimension` of sample `index` of `pixel`
+	fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f64;
+
+	/// values of `dimension` and `dimension + 1`, spread evenly over the unit square
+	fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> (f64, f64);
+}
+
+/// the sampler selected by the settings
+pub fn from_settings(settings: &Settings) -> Arc<dyn Sampler> {
+	let samples_per_pixel = match settings.antialiasing() {
+		Antialiasing::MSAA { samples_per_pixel } => (*samples_per_pixel).max(1) as u32,
+		Antialiasing::ADAPTIVE { max_samples, .. } => (*max_samples).max(1) as u32,
+		Antialiasing::NONE => 1,
+	};
+	let seed = settings.seed();
+	match settings.sampling() {
+		Sampling::RANDOM => Arc::new(RandomSampler::new()),
+		Sampling::STRATIFIED => Arc::new(StratifiedSampler::from(samples_per_pixel, seed)),
+		Sampling::HALTON => Arc::new(HaltonSampler::from(seed)),
+		Sampling::SOBOL => Arc::new(SobolSampler::from(seed)),
+		Sampling::CMJ => Arc::new(CmjSampler::from(samples_per_pixel, seed)),
+	}
+}
+
+struct ActiveSample {
+	sampler: Arc<dyn Sampler>,
+	pixel: u64,
+	index: u64,
+	dimension: u32,
+}
+
+thread_local! {
+	static ACTIVE: RefCell<Option<ActiveSample>> = const { RefCell::new(None) };
+}
+
+/// makes the following `next_1d` and `next_2d` calls on this thread draw the
+/// dimensions of sample `index` of `pixel`, starting with the first one
+pub fn start_sample(sampler: &Arc<dyn Sampler>, pixel: u64, index: u64) {
+	ACTIVE.with(|active| {
+		*active.borrow_mut() = Some(ActiveSample {
+			sampler: Arc::clone(sampler),
+			pixel,
+			index,
+			dimension: 0,
+		})
+	});
+}
+
+/// next dimension of the current sample, a plain random number if no sample was started
+pub fn next_1d() -> f64 {
+	ACTIVE.with(|active| match active.borrow_mut().as_mut() {
+		Some(sample) => {
+			let value = sample
+				.sampler
+				.get_1d(sample.pixel, sample.index, sample.dimension);
+			sample.dimension += 1;
+			value
+		}
+		None => uniform(),
+	})
+}
+
+/// next two dimensions of the current sample, plain random numbers if no sample was started
+pub fn next_2d() -> (f64, f64) {
+	ACTIVE.with(|active| match active.borrow_mut().as_mut() {
+		Some(sample) => {
+			let value = sample
+				.sampler
+				.get_2d(sample.pixel, sample.index, sample.dimension);
+			sample.dimension += 2;
+			value
+		}
+		None => (uniform(), uniform()),
+	})
+}
+
+// `rand` includes 1, samples stay below
+fn uniform() -> f64 {
+	rand().min(ONE_MINUS_EPSILON)
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// 32 random bits for one dimension of a pixel
+fn scramble(seed: u64, pixel: u64, dimension: u32) -> u32 {
+	(sample_seed(seed, pixel, dimension as u64) >> 32) as u32
+}
+
+fn to_unit(bits: u32) -> f64 {
+	bits as f64 / 4_294_967_296.0
+}
+
+/// Kensler's hashed permutation of `0..length`, a different permutation for every `p`
+fn permute(mut i: u32, length: u32, p: u32) -> u32 {
+	let mut w = length - 1;
+	w |= w >> 1;
+	w |= w >> 2;
+	w |= w >> 4;
+	w |= w >> 8;
+	w |= w >> 16;
+	loop {
+		i ^= p;
+		i = i.wrapping_mul(0xe170_893d);
+		i ^= p >> 16;
+		i ^= (i & w) >> 4;
+		i ^= p >> 8;
+		i = i.wrapping_mul(0x0929_eb3f);
+		i ^= p >> 23;
+		i ^= (i & w) >> 1;
+		i = i.wrapping_mul(1 | p >> 27);
+		i = i.wrapping_mul(0x6935_fa69);
+		i ^= (i & w) >> 11;
+		i = i.wrapping_mul(0x74dc_b303);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0x9e50_1cc3);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0xc860_a3df);
+		i &= w;
+		i ^= i >> 5;
+		if i < length {
+			return (i.wrapping_add(p)) % length;
+		}
+	}
+}
+
+/// Kensler's hash of `i` to a value in `[0, 1)`, a different sequence for every `p`
+fn random_float(mut i: u32, p: u32) -> f64 {
+	i ^= p;
+	i ^= i >> 17;
+	i ^= i >> 10;
+	i = i.wrapping_mul(0xb365_34e5);
+	i ^= i >> 12;
+	i ^= i >> 21;
+	i = i.wrapping_mul(0x93fc_4795);
+	i ^= 0xdf6e_307f;
+	i ^= i >> 17;
+	i = i.wrapping_mul(1 | p >> 18);
+	to_unit(i)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const SAMPLES: u32 = 16;
+
+	fn samplers() -> Vec<(&'static str, Arc<dyn Sampler>)> {
+		vec![
+			("random", Arc::new(RandomSampler::new())),
+			("stratified", Arc::new(StratifiedSampler::from(SAMPLES, 1))),
+			("halton", Arc::new(HaltonSampler::from(1))),
+			("sobol", Arc::new(SobolSampler::from(1))),
+			("cmj", Arc::new(CmjSampler::from(SAMPLES, 1))),
+		]
+	}
+
+	#[test]
+	fn test_samples_stay_in_unit_interval() {
+		for (name, sampler) in samplers() {
+			for pixel in 0..8 {
+				for index in 0..SAMPLES as u64 * 2 {
+					for dimension in 0..40 {
+						let x = sampler.get_1d(pixel, index, dimension);
+						let (y, z) = sampler.get_2d(pixel, index, dimension);
+						for v in [x, y, z] {
+							assert!((0.0..1.0).contains(&v), "{} yielded {}", name, v);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	fn count_1d(sampler: &dyn Sampler, samples: u32, dimension: u32) -> Vec<u32> {
+		let mut intervals = vec![0; samples as usize];
+		for index in 0..samples as u64 {
+			let x = sampler.get_1d(7, index, dimension);
+			intervals[(x * samples as f64) as usize] += 1;
+		}
+		intervals
+	}
+
+	#[test]
+	fn test_pixel_is_stratified() {
+		// one of 16 samples in every cell of a 4 x 4 grid and every sixteenth of a dimension
+		for (name, sampler) in samplers().into_iter().skip(1) {
+			if name == "halton" {
+				continue;
+			}
+			for dimension in [0, 2, 6] {
+				let mut cells = [0; 16];
+				for index in 0..SAMPLES as u64 {
+					let (x, y) = sampler.get_2d(7, index, dimension);
+					cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] += 1;
+				}
+				assert_eq!(cells, [1; 16], "{} dimension {}", name, dimension);
+				assert_eq!(count_1d(&*sampler, SAMPLES, dimension), vec![1; 16]);
+			}
+		}
+
+		// the radical inverses in base 2 and 3 stratify powers of their bases
+		let halton = HaltonSampler::from(1);
+		assert_eq!(count_1d(&halton, 16, 0), vec![1; 16]);
+		assert_eq!(count_1d(&halton, 9, 1), vec![1; 9]);
+	}
+
+	#[test]
+	fn test_pixels_are_decorrelated() {
+		for (name, sampler) in samplers().into_iter().skip(1) {
+			let first: Vec<(f64, f64)> = (0..4).map(|i| sampler.get_2d(0, i, 0)).collect();
+			let second: Vec<(f64, f64)> = (0..4).map(|i| sampler.get_2d(1, i, 0)).collect();
+			assert_ne!(first, second, "{}", name);
+		}
+	}
+
+	#[test]
+	fn test_low_discrepancy_reduces_error() {
+		// integrates x * y over the unit square with 16 samples per pixel
+		let error = |sampler: &Arc<dyn Sampler>| -> f64 {
+			(0..200u64)
+				.map(|pixel| {
+					start_sample(sampler, pixel, 0);
+					let estimate: f64 = (0..SAMPLES as u64)
+						.map(|index| {
+							let (x, y) = sampler.get_2d(pixel, index, 0);
+							x * y
+						})
+						.sum::<f64>() / SAMPLES as f64;
+					(estimate - 0.25).abs()
+				})
+				.sum::<f64>()
+				/ 200.0
+		};
+		let samplers = samplers();
+		crate::math::reseed(3);
+		let random = error(&samplers[0].1);
+		for (name, sampler) in samplers.iter().skip(1) {
+			assert!(error(sampler) < random / 2.0, "{}", name);
+		}
+	}
+
+	#[test]
+	fn test_next_draws_consecutive_dimensions() {
+		let sampler: Arc<dyn Sampler> = Arc::new(SobolSampler::from(5));
+		start_sample(&sampler, 3, 2);
+		let (x, y) = next_2d();
+		let z = next_1d();
+		assert_eq!((x, y), sampler.get_2d(3, 2, 0));
+		assert_eq!(z, sampler.get_1d(3, 2, 2));
+
+		start_sample(&sampler, 3, 2);
+		assert_eq!(next_2d(), (x, y));
+	}
+
+	#[test]
+	fn test_permute_is_bijective() {
+		for length in [1, 2, 7, 16, 100] {
+			let mut seen: Vec<u32> = (0..length)
+				.map(|i| permute(i, length, 0xdead_beef))
+				.collect();
+			seen.sort_unstable();
+			assert_eq!(seen, (0..length).collect::<Vec<u32>>());
+		}
+	}
+}
@@ -0,0 +1,27 @@
+use super::{uniform, Sampler};
+
+/// independent random numbers from the thread's generator, which the renderer reseeds
+/// for every sample
+pub struct RandomSampler;
+
+impl Default for RandomSampler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl RandomSampler {
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl Sampler for RandomSampler {
+	fn get_1d(&self, _pixel: u64, _index: u64, _dimension: u32) -> f64 {
+		uniform()
+	}
+
+	fn get_2d(&self, _pixel: u64, _index: u64, _dimension: u32) -> (f64, f64) {
+		(uniform(), uniform())
+	}
+}
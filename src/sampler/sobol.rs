@@ -0,0 +1,87 @@
+use super::{scramble, to_unit, Sampler};
+
+/// The first two Sobol dimensions with Owen scrambling, following Burley's Practical
+/// Hash-based Owen Scrambling. Every pair of dimensions shuffles the sample index and
+/// scrambles the points with its own seed, which pads the two dimensions out to as
+/// many as needed while keeping the samples of a pixel stratified in each pair.
+pub struct SobolSampler {
+	seed: u64,
+}
+
+impl SobolSampler {
+	pub fn from(seed: u64) -> Self {
+		Self { seed }
+	}
+
+	fn point(&self, pixel: u64, index: u64, dimension: u32) -> (u32, u32) {
+		let seed = scramble(self.seed, pixel, dimension);
+		let i = nested_uniform_scramble(index as u32, seed);
+		(
+			nested_uniform_scramble(i.reverse_bits(), hash(seed, 0)),
+			nested_uniform_scramble(sobol_second(i), hash(seed, 1)),
+		)
+	}
+}
+
+/// second Sobol dimension, its generator matrix is Pascal's triangle modulo 2
+fn sobol_second(mut index: u32) -> u32 {
+	let mut v = 1 << 31;
+	let mut result = 0;
+	while index != 0 {
+		if index & 1 != 0 {
+			result ^= v;
+		}
+		index >>= 1;
+		v ^= v >> 1;
+	}
+	result
+}
+
+/// Laine Karras style permutation with the constants of Vegdahl, every bit only depends
+/// on the bits below it
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+	x = x.wrapping_add(seed);
+	x ^= x.wrapping_mul(0x6c50_b47c);
+	x ^= x.wrapping_mul(0xb82f_1e52);
+	x ^= x.wrapping_mul(0xc7af_e638);
+	x ^= x.wrapping_mul(0x8d22_f6e6);
+	x
+}
+
+/// Owen scrambling, every bit is flipped depending on the bits above it
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+	laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn hash(seed: u32, value: u32) -> u32 {
+	let mut x = seed ^ value.wrapping_mul(0x9e37_79b9);
+	x ^= x >> 16;
+	x = x.wrapping_mul(0x21f0_aaad);
+	x ^= x >> 15;
+	x = x.wrapping_mul(0x735a_2d97);
+	x ^ (x >> 15)
+}
+
+impl Sampler for SobolSampler {
+	fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f64 {
+		to_unit(self.point(pixel, index, dimension).0)
+	}
+
+	fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> (f64, f64) {
+		let (x, y) = self.point(pixel, index, dimension);
+		(to_unit(x), to_unit(y))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_unscrambled_sequence() {
+		let first: Vec<u32> = (0..4).map(|i: u32| i.reverse_bits() >> 30).collect();
+		let second: Vec<u32> = (0..4).map(|i| sobol_second(i) >> 30).collect();
+		assert_eq!(first, vec![0, 2, 1, 3]);
+		assert_eq!(second, vec![0, 2, 3, 1]);
+	}
+}
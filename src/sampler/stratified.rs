@@ -0,0 +1,45 @@
+use super::{permute, random_float, scramble, Sampler};
+
+/// Jittered stratification, the samples of a pixel fall into a shuffled grid of strata
+/// with one random point in each. Sample indices past `samples_per_pixel` start over
+/// with a new shuffle.
+pub struct StratifiedSampler {
+	samples_per_pixel: u32,
+	seed: u64,
+}
+
+impl StratifiedSampler {
+	pub fn from(samples_per_pixel: u32, seed: u64) -> Self {
+		assert!(samples_per_pixel > 0);
+		Self {
+			samples_per_pixel,
+			seed,
+		}
+	}
+
+	/// stratum of the sample and the hash for its jitter
+	fn stratum(&self, pixel: u64, index: u64, dimension: u32, strata: u32) -> (u32, u32) {
+		let round = index / self.samples_per_pixel as u64;
+		let p = scramble(self.seed ^ round, pixel, dimension);
+		let i = (index % self.samples_per_pixel as u64) as u32;
+		(permute(i % strata, strata, p), p)
+	}
+}
+
+impl Sampler for StratifiedSampler {
+	fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f64 {
+		let n = self.samples_per_pixel;
+		let (stratum, p) = self.stratum(pixel, index, dimension, n);
+		(stratum as f64 + random_float(stratum, p)) / n as f64
+	}
+
+	fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> (f64, f64) {
+		// a grid with at least as many cells as samples
+		let m = (self.samples_per_pixel as f64).sqrt() as u32;
+		let n = self.samples_per_pixel.div_ceil(m);
+		let (cell, p) = self.stratum(pixel, index, dimension, m * n);
+		let x = (cell % m) as f64 + random_float(cell, p);
+		let y = (cell / m) as f64 + random_float(cell, p.rotate_left(16));
+		(x / m as f64, y / n as f64)
+	}
+}
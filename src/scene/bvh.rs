@@ -0,0 +1,337 @@
+#![allow(dead_code)]
+use crate::math::{Aabb, Point3, Ray};
+use crate::scene::HitRecord;
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// cost of visiting an interior node relative to intersecting one primitive
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// Bounding volume hierarchy over a list of primitive bounds.
+/// The hierarchy only stores indices, so anything that can report an `Aabb`
+/// per primitive (scene entities, mesh triangles) can be accelerated with it.
+pub struct Bvh {
+	nodes: Vec<BvhNode>,
+	indices: Vec<usize>,
+}
+
+struct BvhNode {
+	bounds: Aabb,
+	kind: NodeKind,
+}
+
+enum NodeKind {
+	Leaf { first: usize, count: usize },
+	// the first child directly follows its parent in `nodes`
+	Interior { second_child: usize, axis: usize },
+}
+
+struct BuildPrimitive {
+	index: usize,
+	bounds: Aabb,
+	centroid: Point3,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+	bounds: Aabb,
+	count: usize,
+}
+
+impl Bvh {
+	/// builds the hierarchy with the surface area heuristic evaluated over binned centroids
+	pub fn build(bounds: &[Aabb]) -> Self {
+		let mut primitives: Vec<BuildPrimitive> = bounds
+			.iter()
+			.enumerate()
+			.map(|(index, b)| BuildPrimitive {
+				index,
+				bounds: *b,
+				centroid: b.centroid(),
+			})
+			.collect();
+
+		let mut bvh = Self {
+			nodes: Vec::with_capacity(2 * bounds.len()),
+			indices: Vec::with_capacity(bounds.len()),
+		};
+		if !primitives.is_empty() {
+			bvh.build_recursive(&mut primitives);
+		}
+		bvh
+	}
+
+	pub fn bounds(&self) -> Option<Aabb> {
+		self.nodes.first().map(|n| n.bounds)
+	}
+
+	pub fn depth(&self) -> usize {
+		self.depth_from(0)
+	}
+
+	/// walks the hierarchy front to back and calls `hit` for every primitive index whose
+	/// bounds the ray passes through, the closure receives the current closest distance
+	pub fn traverse<F>(&self, r: &Ray, t_min: f64, t_max: f64, mut hit: F) -> Option<HitRecord>
+	where
+		F: FnMut(usize, f64, f64) -> Option<HitRecord>,
+	{
+		if self.nodes.is_empty() {
+			return None;
+		}
+
+		let dir_is_neg = [
+			r.direction().x() < 0.0,
+			r.direction().y() < 0.0,
+			r.direction().z() < 0.0,
+		];
+		let mut closest = t_max;
+		let mut result: Option<HitRecord> = None;
+		let mut stack: Vec<usize> = Vec::with_capacity(64);
+		stack.push(0);
+
+		while let Some(current) = stack.pop() {
+			let node = &self.nodes[current];
+			if !node.bounds.hit(r, t_min, closest) {
+				continue;
+			}
+			match node.kind {
+				NodeKind::Leaf { first, count } => {
+					for &index in self.indices[first..first + count].iter() {
+						if let Some(rec) = hit(index, t_min, closest) {
+							closest = rec.t();
+							result = Some(rec);
+						}
+					}
+				}
+				NodeKind::Interior { second_child, axis } => {
+					// visit the child closer to the ray origin first
+					if dir_is_neg[axis] {
+						stack.push(current + 1);
+						stack.push(second_child);
+					} else {
+						stack.push(second_child);
+						stack.push(current + 1);
+					}
+				}
+			}
+		}
+
+		result
+	}
+
+	fn depth_from(&self, node: usize) -> usize {
+		match self.nodes[node].kind {
+			NodeKind::Leaf { .. } => 1,
+			NodeKind::Interior { second_child, .. } => {
+				1 + self.depth_from(node + 1).max(self.depth_from(second_child))
+			}
+		}
+	}
+
+	fn build_recursive(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+		let node_index = self.nodes.len();
+		let bounds = primitives
+			.iter()
+			.fold(Aabb::empty(), |acc, p| acc.surrounding(&p.bounds));
+		self.nodes.push(BvhNode {
+			bounds,
+			kind: NodeKind::Leaf { first: 0, count: 0 },
+		});
+
+		let split = if primitives.len() > 1 {
+			Bvh::find_split(primitives, &bounds)
+		} else {
+			None
+		};
+
+		match split {
+			Some((axis, mid)) => {
+				let (left, right) = primitives.split_at_mut(mid);
+				self.build_recursive(left);
+				let second_child = self.build_recursive(right);
+				self.nodes[node_index].kind = NodeKind::Interior { second_child, axis };
+			}
+			None => {
+				let first = self.indices.len();
+				self.indices.extend(primitives.iter().map(|p| p.index));
+				self.nodes[node_index].kind = NodeKind::Leaf {
+					first,
+					count: primitives.len(),
+				};
+			}
+		}
+
+		node_index
+	}
+
+	/// partitions `primitives` along the cheapest split, yields the axis and the
+	/// partition point or `None` if a leaf is cheaper
+	fn find_split(primitives: &mut [BuildPrimitive], bounds: &Aabb) -> Option<(usize, usize)> {
+		let centroid_bounds = primitives
+			.iter()
+			.fold(Aabb::empty(), |acc, p| acc.including(&p.centroid));
+		let axis = centroid_bounds.longest_axis();
+		let c_min = centroid_bounds.min()[axis];
+		let c_max = centroid_bounds.max()[axis];
+
+		if c_max <= c_min {
+			// all centroids coincide, there is nothing to split on
+			return Bvh::split_equal_counts(primitives, axis);
+		}
+
+		let bin_of = |p: &BuildPrimitive| -> usize {
+			let b = (BIN_COUNT as f64 * (p.centroid[axis] - c_min) / (c_max - c_min)) as usize;
+			b.min(BIN_COUNT - 1)
+		};
+
+		let mut bins = [Bin {
+			bounds: Aabb::empty(),
+			count: 0,
+		}; BIN_COUNT];
+		for p in primitives.iter() {
+			let bin = &mut bins[bin_of(p)];
+			bin.count += 1;
+			bin.bounds = bin.bounds.surrounding(&p.bounds);
+		}
+
+		// sweep from the right to get the area and count of every right-hand side
+		let mut right_area = [0.0; BIN_COUNT];
+		let mut right_count = [0; BIN_COUNT];
+		let mut acc = Aabb::empty();
+		let mut count = 0;
+		for i in (1..BIN_COUNT).rev() {
+			acc = acc.surrounding(&bins[i].bounds);
+			count += bins[i].count;
+			right_area[i] = acc.surface_area();
+			right_count[i] = count;
+		}
+
+		let mut best_cost = f64::INFINITY;
+		let mut best_bin = 0;
+		let mut acc = Aabb::empty();
+		let mut count = 0;
+		for i in 0..BIN_COUNT - 1 {
+			acc = acc.surrounding(&bins[i].bounds);
+			count += bins[i].count;
+			let cost =
+				acc.surface_area() * count as f64 + right_area[i + 1] * right_count[i + 1] as f64;
+			if cost < best_cost {
+				best_cost = cost;
+				best_bin = i;
+			}
+		}
+
+		let area = bounds.surface_area();
+		let split_cost = if area > 0.0 {
+			TRAVERSAL_COST + best_cost / area
+		} else {
+			TRAVERSAL_COST
+		};
+		let leaf_cost = primitives.len() as f64;
+		if primitives.len() <= MAX_LEAF_SIZE && leaf_cost <= split_cost {
+			return None;
+		}
+
+		let mid = partition(primitives, |p| bin_of(p) <= best_bin);
+		if mid == 0 || mid == primitives.len() {
+			return Bvh::split_equal_counts(primitives, axis);
+		}
+
+		Some((axis, mid))
+	}
+
+	fn split_equal_counts(
+		primitives: &mut [BuildPrimitive],
+		axis: usize,
+	) -> Option<(usize, usize)> {
+		if primitives.len() <= MAX_LEAF_SIZE {
+			return None;
+		}
+		let mid = primitives.len() / 2;
+		primitives
+			.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+		Some((axis, mid))
+	}
+}
+
+/// moves all elements matching `pred` to the front and yields their count
+fn partition<T, F: Fn(&T) -> bool>(items: &mut [T], pred: F) -> usize {
+	let mut first = 0;
+	for i in 0..items.len() {
+		if pred(&items[i]) {
+			items.swap(first, i);
+			first += 1;
+		}
+	}
+	first
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::math::Vec3;
+	use crate::scene::{Hit, Lambertian, Sphere};
+	use std::sync::Arc;
+
+	fn spheres() -> Vec<Sphere> {
+		let mat = Arc::new(Lambertian::new());
+		(0..50)
+			.map(|i| {
+				let f = i as f64;
+				Sphere::from(
+					Point3::from((f * 1.7) % 9.0 - 4.5, (f * 0.9) % 5.0 - 2.5, -f),
+					0.4,
+					mat.clone(),
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_build_contains_every_primitive_once() {
+		let spheres = spheres();
+		let bounds: Vec<Aabb> = spheres.iter().map(|s| s.bounding_box().unwrap()).collect();
+		let bvh = Bvh::build(&bounds);
+
+		let mut indices = bvh.indices.clone();
+		indices.sort_unstable();
+		assert_eq!(indices, (0..spheres.len()).collect::<Vec<usize>>());
+		assert!(bvh.depth() < spheres.len());
+	}
+
+	#[test]
+	fn test_traverse_matches_linear_search() {
+		let spheres = spheres();
+		let bounds: Vec<Aabb> = spheres.iter().map(|s| s.bounding_box().unwrap()).collect();
+		let bvh = Bvh::build(&bounds);
+
+		for i in 0..200 {
+			let f = i as f64;
+			let r = Ray::from(
+				&Point3::from(0.0, 0.0, 10.0),
+				&Vec3::from((f * 0.37).sin() * 0.4, (f * 0.71).cos() * 0.3, -1.0),
+			);
+			let linear = spheres
+				.iter()
+				.filter_map(|s| s.hit(&r, 0.001, f64::INFINITY))
+				.map(|rec| rec.t())
+				.fold(f64::INFINITY, f64::min);
+			let accelerated = bvh
+				.traverse(&r, 0.001, f64::INFINITY, |index, t_min, t_max| {
+					spheres[index].hit(&r, t_min, t_max)
+				})
+				.map(|rec| rec.t())
+				.unwrap_or(f64::INFINITY);
+
+			assert_eq!(linear, accelerated);
+		}
+	}
+
+	#[test]
+	fn test_empty() {
+		let bvh = Bvh::build(&[]);
+		let r = Ray::from(&Point3::new(), &Vec3::from(0.0, 0.0, -1.0));
+		assert!(bvh.bounds().is_none());
+		assert!(bvh.traverse(&r, 0.0, 1.0, |_, _, _| None).is_none());
+	}
+}
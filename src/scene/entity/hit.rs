@@ -1,21 +1,23 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::math::{dot, Point3, Ray, Vec3};
+use crate::math::{dot, Aabb, Point3, Ray, Vec3};
 use crate::scene::Material;
 
 pub struct HitRecord {
 	p: Point3,
 	normal: Vec3,
 	t: f64,
+	uv: (f64, f64),
 	front_face: bool,
-	mat: Rc<dyn Material>,
+	mat: Arc<dyn Material>,
 }
 
 impl HitRecord {
-	pub fn new(mat: Rc<dyn Material>) -> Self {
+	pub fn new(mat: Arc<dyn Material>) -> Self {
 		Self {
 			p: Point3::new(),
 			t: 0.0,
+			uv: (0.0, 0.0),
 			normal: Vec3::new(),
 			front_face: false,
 			mat,
@@ -30,8 +32,8 @@ impl HitRecord {
 		self.front_face
 	}
 
-	pub fn material(&self) -> Rc<dyn Material> {
-		Rc::clone(&self.mat)
+	pub fn material(&self) -> Arc<dyn Material> {
+		Arc::clone(&self.mat)
 	}
 
 	pub fn t_mut(&mut self) -> &mut f64 {
@@ -42,6 +44,16 @@ impl HitRecord {
 		self.t
 	}
 
+	/// surface coordinates of the hit point, within [0, 1] on the built in shapes while
+	/// meshes pass on their texture coordinates, which may lie outside to tile a texture
+	pub fn uv(&self) -> (f64, f64) {
+		self.uv
+	}
+
+	pub fn uv_mut(&mut self) -> &mut (f64, f64) {
+		&mut self.uv
+	}
+
 	pub fn point(&self) -> &Point3 {
 		&self.p
 	}
@@ -58,8 +70,36 @@ impl HitRecord {
 			-outward_normal
 		}
 	}
+
+	/// replaces the geometric normal with an outward shading normal,
+	/// has to be called after `calc_normal` which decides the facing
+	pub fn shade_normal(&mut self, outward_normal: Vec3) {
+		self.normal = if self.front_face {
+			outward_normal
+		} else {
+			-outward_normal
+		}
+	}
 }
 
 pub trait Hit: Send + Sync {
 	fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+	/// box enclosing the entity, `None` for unbounded entities which are then always tested
+	fn bounding_box(&self) -> Option<Aabb>;
+
+	/// whether the entity emits light and is sampled directly by the renderer
+	fn is_light(&self) -> bool {
+		false
+	}
+
+	/// random direction from `origin` towards a point on the entity, `None` if the
+	/// entity cannot be sampled from there
+	fn sample_direction(&self, _origin: &Point3) -> Option<Vec3> {
+		None
+	}
+
+	/// solid angle density with which `sample_direction` yields `direction` from `origin`
+	fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+		0.0
+	}
 }
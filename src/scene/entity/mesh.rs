@@ -0,0 +1,297 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use crate::math::{norm, Aabb, Point3, Ray, Vec3, INFINITY};
+use crate::sampler::next_1d;
+use crate::scene::bvh::Bvh;
+use crate::scene::entity::triangle::{
+	area_to_solid_angle, face_normal, intersect_triangle, sample_triangle,
+};
+use crate::scene::{Hit, HitRecord, Material};
+
+/// Triangles sharing one vertex buffer, every face holds three indices into it.
+/// Normals and texture coordinates are optional and stored per vertex.
+pub struct TriangleMesh {
+	positions: Vec<Point3>,
+	indices: Vec<[usize; 3]>,
+	normals: Option<Vec<Vec3>>,
+	uvs: Option<Vec<(f64, f64)>>,
+	mat: Arc<dyn Material>,
+	bvh: Bvh,
+	// running sum of the face areas for picking faces by area
+	areas: Vec<f64>,
+}
+
+impl TriangleMesh {
+	/// panics when a face indexes past `positions`, `ObjLoader` reports such faces as
+	/// parse errors before building a mesh
+	pub fn from(positions: Vec<Point3>, indices: Vec<[usize; 3]>, mat: Arc<dyn Material>) -> Self {
+		assert!(
+			indices.iter().flatten().all(|&i| i < positions.len()),
+			"face index out of bounds"
+		);
+		let bounds: Vec<Aabb> = indices
+			.iter()
+			.map(|[a, b, c]| Aabb::from(positions[*a], positions[*b]).including(&positions[*c]))
+			.collect();
+
+		let areas = indices
+			.iter()
+			.scan(0.0, |total, [a, b, c]| {
+				let vertices = [&positions[*a], &positions[*b], &positions[*c]];
+				*total += face_normal(vertices).len() / 2.0;
+				Some(*total)
+			})
+			.collect();
+
+		Self {
+			bvh: Bvh::build(&bounds),
+			areas,
+			positions,
+			indices,
+			normals: None,
+			uvs: None,
+			mat,
+		}
+	}
+
+	/// per vertex normals which get interpolated across the faces for smooth shading,
+	/// panics unless there is one for every position
+	pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+		assert_eq!(
+			normals.len(),
+			self.positions.len(),
+			"one normal per position"
+		);
+		self.normals = Some(normals);
+		self
+	}
+
+	/// per vertex texture coordinates, panics unless there are some for every position
+	pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+		assert_eq!(
+			uvs.len(),
+			self.positions.len(),
+			"one texture coordinate per position"
+		);
+		self.uvs = Some(uvs);
+		self
+	}
+
+	pub fn triangle_count(&self) -> usize {
+		self.indices.len()
+	}
+
+	fn face(&self, face: usize) -> [&Point3; 3] {
+		let [a, b, c] = self.indices[face];
+		[&self.positions[a], &self.positions[b], &self.positions[c]]
+	}
+
+	fn interpolate_normal(&self, face: usize, u: f64, v: f64) -> Option<Vec3> {
+		let normals = self.normals.as_ref()?;
+		let [a, b, c] = self.indices[face];
+		let n = (1.0 - u - v) * normals[a] + u * normals[b] + v * normals[c];
+		if n.near_zero() {
+			return None;
+		}
+		Some(norm(&n))
+	}
+
+	fn interpolate_uv(&self, face: usize, u: f64, v: f64) -> Option<(f64, f64)> {
+		let uvs = self.uvs.as_ref()?;
+		let [a, b, c] = self.indices[face];
+		let w = 1.0 - u - v;
+		Some((
+			w * uvs[a].0 + u * uvs[b].0 + v * uvs[c].0,
+			w * uvs[a].1 + u * uvs[b].1 + v * uvs[c].1,
+		))
+	}
+
+	fn area(&self) -> f64 {
+		self.areas.last().copied().unwrap_or(0.0)
+	}
+
+	/// closest hit of the ray with the faces, the index of the face and the distance
+	fn closest_face(&self, r: &Ray, t_min: f64) -> Option<(usize, f64)> {
+		let mut closest = None;
+		self.bvh
+			.traverse(r, t_min, INFINITY, |face, t_min, t_max| {
+				let rec = self.hit_face(face, r, t_min, t_max)?;
+				closest = Some((face, rec.t()));
+				Some(rec)
+			})?;
+		closest
+	}
+
+	fn hit_face(&self, face: usize, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+		let vertices = self.face(face);
+		let (t, u, v) = intersect_triangle(r, vertices, t_min, t_max)?;
+
+		let mut rec = HitRecord::new(Arc::clone(&self.mat));
+		*rec.t_mut() = t;
+		*rec.point_mut() = r.at(t);
+		// without texture coordinates the barycentrics are used
+		*rec.uv_mut() = self.interpolate_uv(face, u, v).unwrap_or((u, v));
+		rec.calc_normal(r, norm(&face_normal(vertices)));
+		if let Some(n) = self.interpolate_normal(face, u, v) {
+			rec.shade_normal(n);
+		}
+		Some(rec)
+	}
+}
+
+impl Hit for TriangleMesh {
+	fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+		self.bvh.traverse(r, t_min, t_max, |face, t_min, t_max| {
+			self.hit_face(face, r, t_min, t_max)
+		})
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		self.bvh.bounds()
+	}
+
+	fn is_light(&self) -> bool {
+		self.mat.is_emissive()
+	}
+
+	fn sample_direction(&self, origin: &Point3) -> Option<Vec3> {
+		// faces are picked by area so points are uniform over the whole surface
+		let area = self.area();
+		if area == 0.0 {
+			return None;
+		}
+		let x = next_1d() * area;
+		let face = self
+			.areas
+			.partition_point(|&total| total < x)
+			.min(self.areas.len() - 1);
+		Some(sample_triangle(self.face(face)) - origin)
+	}
+
+	fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+		let area = self.area();
+		if area == 0.0 {
+			return 0.0;
+		}
+		// every face the ray passes through could have been sampled
+		let r = Ray::from(origin, direction);
+		let mut pdf = 0.0;
+		let mut t_min = 0.0;
+		while let Some((face, t)) = self.closest_face(&r, t_min) {
+			pdf += area_to_solid_angle(1.0 / area, direction, t, &face_normal(self.face(face)));
+			t_min = t + 1e-9;
+		}
+		pdf
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::scene::Lambertian;
+
+	// unit quad in the z = -2 plane made of two triangles
+	fn quad() -> TriangleMesh {
+		TriangleMesh::from(
+			vec![
+				Point3::from(-1.0, -1.0, -2.0),
+				Point3::from(1.0, -1.0, -2.0),
+				Point3::from(1.0, 1.0, -2.0),
+				Point3::from(-1.0, 1.0, -2.0),
+			],
+			vec![[0, 1, 2], [0, 2, 3]],
+			Arc::new(Lambertian::new()),
+		)
+	}
+
+	#[test]
+	fn test_hit_both_faces() {
+		let mesh = quad();
+		for dir in [Vec3::from(0.3, -0.2, -1.0), Vec3::from(-0.3, 0.2, -1.0)] {
+			let r = Ray::from(&Point3::new(), &dir);
+			let rec = mesh.hit(&r, 0.0, 10.0).expect("ray should hit quad");
+			assert_eq!(rec.t(), 2.0);
+			assert_eq!(rec.normal(), &Vec3::from(0.0, 0.0, 1.0));
+		}
+
+		let miss = Ray::from(&Point3::new(), &Vec3::from(1.0, 0.0, -0.4));
+		assert!(mesh.hit(&miss, 0.0, 10.0).is_none());
+	}
+
+	#[test]
+	fn test_interpolated_normal() {
+		let tilted = norm(&Vec3::from(1.0, 0.0, 1.0));
+		let flat = Vec3::from(0.0, 0.0, 1.0);
+		let mesh = quad().with_normals(vec![flat, tilted, tilted, flat]);
+
+		let r = Ray::from(&Point3::from(1.0, 0.0, 0.0), &Vec3::from(0.0, 0.0, -1.0));
+		let rec = mesh.hit(&r, 0.0, 10.0).unwrap();
+		assert_eq!(rec.normal().round_to(10_000), tilted.round_to(10_000));
+
+		let r = Ray::from(&Point3::from(-1.0, 0.0, 0.0), &Vec3::from(0.0, 0.0, -1.0));
+		let rec = mesh.hit(&r, 0.0, 10.0).unwrap();
+		assert_eq!(rec.normal().round_to(10_000), flat);
+	}
+
+	#[test]
+	fn test_interpolated_uv() {
+		let mesh = quad().with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+		assert_eq!(mesh.interpolate_uv(0, 1.0, 0.0), Some((1.0, 0.0)));
+		assert_eq!(mesh.interpolate_uv(1, 0.5, 0.5), Some((0.5, 1.0)));
+	}
+
+	#[test]
+	fn test_bounding_box() {
+		let b = quad().bounding_box().unwrap();
+		assert_eq!(b.min(), &Point3::from(-1.0, -1.0, -2.0));
+		assert_eq!(b.max(), &Point3::from(1.0, 1.0, -2.0));
+	}
+
+	#[test]
+	fn test_pdf_value() {
+		let light = crate::scene::DiffuseLight::from(crate::Color::from(1.0, 1.0, 1.0));
+		let mesh = TriangleMesh::from(quad().positions, quad().indices, Arc::new(light));
+		let origin = Point3::new();
+		assert!(mesh.is_light() && !quad().is_light());
+		assert_eq!(mesh.area(), 4.0);
+
+		// the quad center is seen head on from a distance of 2
+		let pdf = mesh.pdf_value(&origin, &Vec3::from(0.0, 0.0, -1.0));
+		assert!((pdf - 1.0).abs() < 1e-12);
+		assert_eq!(mesh.pdf_value(&origin, &Vec3::from(0.0, 0.0, 1.0)), 0.0);
+
+		for _ in 0..100 {
+			let direction = mesh.sample_direction(&origin).unwrap();
+			assert!((direction.z() + 2.0).abs() < 1e-12);
+			assert!(direction.x().abs() <= 1.0 && direction.y().abs() <= 1.0);
+		}
+	}
+
+	#[test]
+	fn test_closed_mesh_pdf_counts_every_face() {
+		let light = crate::scene::DiffuseLight::from(crate::Color::from(1.0, 1.0, 1.0));
+		let min = Point3::from(-1.0, -1.0, -3.0);
+		let cube = crate::Scene::cuboid(min, Point3::from(1.0, 1.0, -1.0), Arc::new(light));
+		// passing the front and the back face, 1 and 9 away, each facing the origin
+		let pdf = cube.pdf_value(&Point3::new(), &Vec3::from(0.0, 0.0, -1.0));
+		assert!((pdf - (1.0 + 9.0) / 24.0).abs() < 1e-12);
+	}
+
+	#[test]
+	#[should_panic(expected = "face index out of bounds")]
+	fn test_index_out_of_bounds() {
+		TriangleMesh::from(
+			vec![Point3::new()],
+			vec![[0, 1, 2]],
+			Arc::new(Lambertian::new()),
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "one normal per position")]
+	fn test_normal_count_mismatch() {
+		quad().with_normals(vec![Vec3::from(0.0, 0.0, 1.0)]);
+	}
+}
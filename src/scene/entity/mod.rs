@@ -1,6 +1,8 @@
 use hit::Hit;
 
 pub mod hit;
+pub mod mesh;
 pub mod sphere;
+pub mod triangle;
 
 pub type Entity = Box<dyn Hit>;
@@ -1,14 +1,21 @@
 #![allow(dead_code)]
 
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::math::{dot, Point3, Ray, Vec3};
+use crate::math::{clamp, dot, norm, orthonormal_basis, Aabb, Point3, Ray, Vec3, INFINITY, PI};
+use crate::sampler::next_2d;
 use crate::scene::{Hit, HitRecord, Lambertian, Material};
 
 pub struct Sphere {
 	center: Point3,
 	radius: f64,
-	mat: Rc<dyn Material>,
+	mat: Arc<dyn Material>,
+}
+
+impl Default for Sphere {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Sphere {
@@ -16,11 +23,11 @@ impl Sphere {
 		Self {
 			center: Point3::new(),
 			radius: 0.0,
-			mat: Rc::new(Lambertian::new()),
+			mat: Arc::new(Lambertian::new()),
 		}
 	}
 
-	pub fn from(c: Point3, r: f64, mat: Rc<dyn Material>) -> Self {
+	pub fn from(c: Point3, r: f64, mat: Arc<dyn Material>) -> Self {
 		Self {
 			center: c,
 			radius: r,
@@ -29,9 +36,9 @@ impl Sphere {
 	}
 
 	fn solve_quadratic(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
-		let oc = r.origin() - &self.center;
+		let oc = r.origin() - self.center;
 		let a = r.direction().len_squared();
-		let half_b = dot(&oc, &r.direction());
+		let half_b = dot(&oc, r.direction());
 		let c = oc.len_squared() - (self.radius * self.radius);
 
 		let discriminant = (half_b * half_b) - (a * c);
@@ -52,38 +59,91 @@ impl Sphere {
 	}
 
 	fn outward_normal(&self, hit_p: &Point3) -> Vec3 {
-		(hit_p - &self.center) / self.radius
+		(hit_p - self.center) / self.radius
+	}
+
+	/// cosine of the half angle of the cone the sphere fills as seen from `origin`,
+	/// `None` if the origin lies inside the sphere
+	fn cone_cos(&self, origin: &Point3) -> Option<f64> {
+		let distance_squared = (self.center - origin).len_squared();
+		let radius_squared = self.radius * self.radius;
+		if distance_squared <= radius_squared {
+			return None;
+		}
+		Some((1.0 - radius_squared / distance_squared).sqrt())
+	}
+
+	/// spherical mapping of a point on the unit sphere, u grows with the angle
+	/// around the y-axis starting at -x and v from the bottom to the top pole
+	fn uv(outward_normal: &Vec3) -> (f64, f64) {
+		let theta = clamp(-outward_normal.y(), -1.0, 1.0).acos();
+		let phi = (-outward_normal.z()).atan2(outward_normal.x()) + PI;
+
+		(phi / (2.0 * PI), theta / PI)
 	}
 }
 
 impl Hit for Sphere {
 	fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
 		if let Some(root) = self.solve_quadratic(r, t_min, t_max) {
-			let mut rec = HitRecord::new(Rc::clone(&self.mat));
+			let mut rec = HitRecord::new(Arc::clone(&self.mat));
 			*rec.t_mut() = root;
 			*rec.point_mut() = r.at(rec.t());
-			rec.calc_normal(&r, self.outward_normal(rec.point()));
+			let outward_normal = self.outward_normal(rec.point());
+			*rec.uv_mut() = Sphere::uv(&outward_normal);
+			rec.calc_normal(r, outward_normal);
 			Some(rec)
 		} else {
 			None
 		}
 	}
-}
 
-unsafe impl Send for Sphere {}
-unsafe impl Sync for Sphere {}
+	fn bounding_box(&self) -> Option<Aabb> {
+		let r = Vec3::from(self.radius, self.radius, self.radius);
+		Some(Aabb::from(self.center - r, self.center + r))
+	}
+
+	fn is_light(&self) -> bool {
+		self.mat.is_emissive()
+	}
+
+	fn sample_direction(&self, origin: &Point3) -> Option<Vec3> {
+		// uniform over the cone of directions that hit the sphere
+		let cos_max = self.cone_cos(origin)?;
+		let w = norm(&(self.center - origin));
+		let (u, v) = orthonormal_basis(&w);
+		let (s, t) = next_2d();
+		let z = 1.0 + s * (cos_max - 1.0);
+		let phi = 2.0 * PI * t;
+		let r = (1.0 - z * z).sqrt();
+		Some(r * phi.cos() * u + r * phi.sin() * v + z * w)
+	}
+
+	fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+		match self.cone_cos(origin) {
+			Some(cos_max)
+				if self
+					.hit(&Ray::from(origin, direction), 0.0, INFINITY)
+					.is_some() =>
+			{
+				1.0 / (2.0 * PI * (1.0 - cos_max))
+			}
+			_ => 0.0,
+		}
+	}
+}
 
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::math::{norm, Vec3};
+	use crate::Color;
 	#[test]
 	fn test_should_return_some_for_hit() {
 		// ray shooting down the negative z-axis
 		let r = Ray::from(&Point3::new(), &Vec3::from(0.0, 0.0, -1.0));
 		let mat = Lambertian::new();
 		// sphere sitting away from origin by -1 on z-axis
-		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Rc::new(mat));
+		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Arc::new(mat));
 
 		assert_eq!(norm(&Vec3::from(0.0, 0.0, -1.0)).len(), 1.0);
 		let hit = sphere.hit(&r, 0.0, 5.0);
@@ -94,7 +154,7 @@ mod test {
 	fn test_should_not_hit() {
 		let r = Ray::from(&Point3::new(), &norm(&Vec3::from(-0.6, 0.7, -1.0)));
 		let mat = Lambertian::new();
-		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Rc::new(mat));
+		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Arc::new(mat));
 		let hit = sphere.hit(&r, 0.0, 5.0);
 
 		assert!(hit.is_none());
@@ -103,10 +163,54 @@ mod test {
 	#[test]
 	fn test_outward_normal() {
 		let mat = Lambertian::new();
-		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Rc::new(mat));
+		let sphere = Sphere::from(Point3::from(0.0, 0.0, -1.0), 0.5, Arc::new(mat));
 
 		let outward_normal = sphere.outward_normal(&Point3::from(0.0, 0.0, -0.5));
 
 		assert_eq!(Vec3::from(0.0, 0.0, 1.0), outward_normal)
 	}
+
+	#[test]
+	fn test_bounding_box() {
+		let sphere = Sphere::from(
+			Point3::from(1.0, 0.0, -1.0),
+			0.5,
+			Arc::new(Lambertian::new()),
+		);
+		let b = sphere.bounding_box().unwrap();
+
+		assert_eq!(b.min(), &Point3::from(0.5, -0.5, -1.5));
+		assert_eq!(b.max(), &Point3::from(1.5, 0.5, -0.5));
+	}
+
+	#[test]
+	fn test_sample_direction() {
+		let light = crate::scene::DiffuseLight::from(Color::from(1.0, 1.0, 1.0));
+		let sphere = Sphere::from(Point3::from(0.0, 0.0, -4.0), 2.0, Arc::new(light));
+		let origin = Point3::new();
+		assert!(sphere.is_light());
+
+		// the sphere fills a cone with a half angle of 30 degrees
+		let expected = 1.0 / (2.0 * PI * (1.0 - 0.75f64.sqrt()));
+		for _ in 0..100 {
+			let direction = sphere.sample_direction(&origin).unwrap();
+			assert!((sphere.pdf_value(&origin, &direction) - expected).abs() < 1e-9);
+		}
+		assert_eq!(sphere.pdf_value(&origin, &Vec3::from(0.0, 1.0, 0.0)), 0.0);
+		assert!(sphere
+			.sample_direction(&Point3::from(0.0, 0.0, -3.0))
+			.is_none());
+		assert!(!Sphere::new().is_light());
+	}
+
+	#[test]
+	fn test_uv() {
+		let uv = |x, y, z| Sphere::uv(&Vec3::from(x, y, z));
+		assert_eq!(uv(-1.0, 0.0, 0.0), (0.0, 0.5));
+		assert_eq!(uv(0.0, 0.0, 1.0), (0.25, 0.5));
+		assert_eq!(uv(1.0, 0.0, 0.0), (0.5, 0.5));
+		assert_eq!(uv(0.0, 0.0, -1.0), (0.75, 0.5));
+		assert_eq!(uv(0.0, 1.0, 0.0).1, 1.0);
+		assert_eq!(uv(0.0, -1.0, 0.0).1, 0.0);
+	}
 }
@@ -0,0 +1,207 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use crate::math::{cross, dot, norm, Aabb, Point3, Ray, Vec3, INFINITY};
+use crate::sampler::next_2d;
+use crate::scene::{Hit, HitRecord, Material};
+
+// rays closer to parallel than this are treated as misses
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+pub struct Triangle {
+	vertices: [Point3; 3],
+	mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+	pub fn from(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+		Self {
+			vertices: [a, b, c],
+			mat,
+		}
+	}
+
+	pub fn vertices(&self) -> &[Point3; 3] {
+		&self.vertices
+	}
+}
+
+/// Möller–Trumbore ray triangle intersection, yields the ray parameter and the
+/// barycentric coordinates of the second and third vertex
+pub fn intersect_triangle(
+	r: &Ray,
+	[a, b, c]: [&Point3; 3],
+	t_min: f64,
+	t_max: f64,
+) -> Option<(f64, f64, f64)> {
+	let edge1 = b - a;
+	let edge2 = c - a;
+	let p = cross(r.direction(), &edge2);
+	let det = dot(&edge1, &p);
+	if det.abs() < PARALLEL_EPSILON {
+		return None;
+	}
+
+	let inv_det = 1.0 / det;
+	let s = r.origin() - a;
+	let u = dot(&s, &p) * inv_det;
+	if !(0.0..=1.0).contains(&u) {
+		return None;
+	}
+
+	let q = cross(&s, &edge1);
+	let v = dot(r.direction(), &q) * inv_det;
+	if v < 0.0 || u + v > 1.0 {
+		return None;
+	}
+
+	let t = dot(&edge2, &q) * inv_det;
+	if t < t_min || t_max < t {
+		return None;
+	}
+
+	Some((t, u, v))
+}
+
+/// unnormalized geometric normal following the counter-clockwise winding of the vertices
+pub fn face_normal([a, b, c]: [&Point3; 3]) -> Vec3 {
+	cross(&(b - a), &(c - a))
+}
+
+/// uniformly distributed point on the triangle
+pub fn sample_triangle([a, b, c]: [&Point3; 3]) -> Point3 {
+	let (s, t) = next_2d();
+	let s = s.sqrt();
+	(1.0 - s) * a + (s * (1.0 - t)) * b + (s * t) * c
+}
+
+/// converts the area density of a point hit at `t` along `direction` into a solid angle
+/// density as seen from the ray origin, `normal` being the unnormalized face normal
+pub fn area_to_solid_angle(area_pdf: f64, direction: &Vec3, t: f64, normal: &Vec3) -> f64 {
+	let distance_squared = t * t * direction.len_squared();
+	let cosine = dot(normal, direction).abs() / (normal.len() * direction.len());
+	if cosine == 0.0 {
+		return 0.0;
+	}
+	area_pdf * distance_squared / cosine
+}
+
+impl Hit for Triangle {
+	fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+		let [a, b, c] = &self.vertices;
+		let (t, u, v) = intersect_triangle(r, [a, b, c], t_min, t_max)?;
+
+		let mut rec = HitRecord::new(Arc::clone(&self.mat));
+		*rec.t_mut() = t;
+		*rec.uv_mut() = (u, v);
+		*rec.point_mut() = r.at(t);
+		rec.calc_normal(r, norm(&face_normal([a, b, c])));
+		Some(rec)
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		let [a, b, c] = &self.vertices;
+		Some(Aabb::from(*a, *b).including(c))
+	}
+
+	fn is_light(&self) -> bool {
+		self.mat.is_emissive()
+	}
+
+	fn sample_direction(&self, origin: &Point3) -> Option<Vec3> {
+		let [a, b, c] = &self.vertices;
+		Some(sample_triangle([a, b, c]) - origin)
+	}
+
+	fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+		let [a, b, c] = &self.vertices;
+		let r = Ray::from(origin, direction);
+		match intersect_triangle(&r, [a, b, c], 0.0, INFINITY) {
+			Some((t, _, _)) => {
+				let normal = face_normal([a, b, c]);
+				area_to_solid_angle(2.0 / normal.len(), direction, t, &normal)
+			}
+			None => 0.0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::scene::Lambertian;
+
+	fn triangle() -> Triangle {
+		Triangle::from(
+			Point3::from(-1.0, -1.0, -2.0),
+			Point3::from(1.0, -1.0, -2.0),
+			Point3::from(0.0, 1.0, -2.0),
+			Arc::new(Lambertian::new()),
+		)
+	}
+
+	#[test]
+	fn test_hit() {
+		let r = Ray::from(&Point3::new(), &Vec3::from(0.0, 0.0, -1.0));
+		let rec = triangle()
+			.hit(&r, 0.0, 10.0)
+			.expect("ray should hit triangle");
+
+		assert_eq!(rec.t(), 2.0);
+		assert_eq!(rec.normal(), &Vec3::from(0.0, 0.0, 1.0));
+		assert!(rec.front_face());
+	}
+
+	#[test]
+	fn test_should_not_hit() {
+		let outside = Ray::from(&Point3::new(), &Vec3::from(0.9, 0.0, -1.0));
+		assert!(triangle().hit(&outside, 0.0, 10.0).is_none());
+
+		let parallel = Ray::from(&Point3::new(), &Vec3::from(1.0, 0.0, 0.0));
+		assert!(triangle().hit(&parallel, 0.0, 10.0).is_none());
+
+		let too_far = Ray::from(&Point3::new(), &Vec3::from(0.0, 0.0, -1.0));
+		assert!(triangle().hit(&too_far, 0.0, 1.0).is_none());
+	}
+
+	#[test]
+	fn test_back_face() {
+		let r = Ray::from(&Point3::from(0.0, 0.0, -4.0), &Vec3::from(0.0, 0.0, 1.0));
+		let rec = triangle()
+			.hit(&r, 0.0, 10.0)
+			.expect("ray should hit triangle");
+
+		assert!(!rec.front_face());
+		assert_eq!(rec.normal(), &Vec3::from(0.0, 0.0, -1.0));
+	}
+
+	#[test]
+	fn test_pdf_value() {
+		let t = triangle();
+		let origin = Point3::new();
+		// area 2 seen head on from a distance of 2
+		let center = Vec3::from(0.0, -1.0 / 3.0, -2.0);
+		let pdf = t.pdf_value(&origin, &center);
+		let distance_squared = center.len_squared();
+		let cosine = 2.0 / center.len();
+		assert!((pdf - distance_squared / (2.0 * cosine)).abs() < 1e-12);
+		assert_eq!(t.pdf_value(&origin, &Vec3::from(0.0, 0.0, 1.0)), 0.0);
+
+		for _ in 0..100 {
+			let direction = t.sample_direction(&origin).unwrap();
+			assert!(t.pdf_value(&origin, &direction) > 0.0);
+		}
+		assert!(!t.is_light());
+	}
+
+	#[test]
+	fn test_barycentrics() {
+		let t = triangle();
+		let [a, b, c] = t.vertices();
+		let r = Ray::from(&Point3::from(0.0, 1.0, 0.0), &Vec3::from(0.0, 0.0, -1.0));
+		let (_, u, v) = intersect_triangle(&r, [a, b, c], 0.0, 10.0).unwrap();
+
+		assert_eq!((u, v), (0.0, 1.0));
+	}
+}
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 use rand_distr::num_traits::Pow;
 
-use super::Material;
-use crate::math::{dot, norm, rand, reflect, refract, Ray};
+use super::{BsdfSample, Material};
+use crate::math::{dot, norm, reflect, refract, Ray};
+use crate::sampler::next_1d;
 use crate::scene::HitRecord;
 use crate::Color;
 
@@ -10,6 +11,12 @@ pub struct Dielectric {
 	refraction_index: f64,
 }
 
+impl Default for Dielectric {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Dielectric {
 	pub fn new() -> Self {
 		Self {
@@ -31,7 +38,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-	fn scatter(&self, r_in: &Ray, rec: HitRecord) -> Option<(Color, Ray)> {
+	fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
 		let refraction_ratio = if rec.front_face() {
 			1.0 / self.refraction_index
 		} else {
@@ -42,15 +49,16 @@ impl Material for Dielectric {
 		let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 		// if the ray can be reflected - look up total internal reflection, critical angle
 		let direction = match refraction_ratio * sin_theta > 1.0
-			|| Dielectric::reflectance(cos_theta, refraction_ratio) > rand()
+			|| Dielectric::reflectance(cos_theta, refraction_ratio) > next_1d()
 		{
 			true => reflect(&unit_dir, rec.normal()),
 			false => refract(&unit_dir, rec.normal(), refraction_ratio),
 		};
 
-		Some((
-			Color::from(1.0, 1.0, 1.0),
-			Ray::from(rec.point(), &direction),
-		))
+		Some(BsdfSample::specular(direction, Color::from(1.0, 1.0, 1.0)))
+	}
+
+	fn is_specular(&self) -> bool {
+		true
 	}
 }
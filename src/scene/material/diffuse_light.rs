@@ -0,0 +1,36 @@
+use super::HitRecord;
+use super::{BsdfSample, Material};
+use crate::math::Ray;
+use crate::scene::{SolidColor, Texture};
+use crate::Color;
+use std::sync::Arc;
+
+/// emits light equally in all directions and does not scatter incoming rays
+pub struct DiffuseLight {
+	emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+	pub fn from(emit: Color) -> Self {
+		DiffuseLight::from_texture(Arc::new(SolidColor::from(emit)))
+	}
+
+	pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
+		Self { emit }
+	}
+}
+
+impl Material for DiffuseLight {
+	fn sample(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<BsdfSample> {
+		None
+	}
+
+	fn emitted(&self, rec: &HitRecord) -> Color {
+		let (u, v) = rec.uv();
+		self.emit.value(u, v, rec.point())
+	}
+
+	fn is_emissive(&self) -> bool {
+		true
+	}
+}
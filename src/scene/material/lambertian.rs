@@ -1,50 +1,94 @@
 use super::HitRecord;
-use super::Material;
-use crate::math::{Ray, Vec3};
+use super::{BsdfSample, Material};
+use crate::math::{dot, norm, Ray, Vec3, PI};
+use crate::sampler::next_2d;
+use crate::scene::{SolidColor, Texture};
 use crate::Color;
+use std::sync::Arc;
 
 pub struct Lambertian {
-	albedo: Color,
+	albedo: Arc<dyn Texture>,
+}
+
+impl Default for Lambertian {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Lambertian {
 	pub fn new() -> Self {
-		Self {
-			albedo: Color::from(0.0, 0.0, 0.0),
-		}
+		Lambertian::from(Color::from(0.0, 0.0, 0.0))
 	}
 
 	pub fn from(albedo: Color) -> Self {
+		Lambertian::from_texture(Arc::new(SolidColor::from(albedo)))
+	}
+
+	pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
 		Self { albedo }
 	}
 }
 
 impl Material for Lambertian {
-	fn scatter(&self, _r_in: &Ray, rec: HitRecord) -> Option<(Color, Ray)> {
-		// from the book but `target` is unnesessary. You can just use the hemisphere vector
-
-		// let target = rec.point() + Vec3::random_in_hemisphere(rec.normal());
-		// let child_ray = Ray::from(rec.point(), &(target - rec.point()));
-		// return 0.5 * ray_color(&child_ray, entities, depth - 1);
+	fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+		// cosine weighted, the cosine and the 1 / pi of the density cancel with `eval`
+		let direction = Vec3::cosine_direction(rec.normal(), next_2d());
+		let pdf = self.pdf(r_in, rec, &direction);
+		if pdf <= 0.0 {
+			return None;
+		}
+		let (u, v) = rec.uv();
+		Some(BsdfSample::from(
+			direction,
+			self.albedo.value(u, v, rec.point()),
+			pdf,
+		))
+	}
 
-		// @author -> from the book, but inaccuarate
+	fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+		let cosine = dot(&norm(direction), rec.normal());
+		if cosine <= 0.0 {
+			return Color::black();
+		}
+		let (u, v) = rec.uv();
+		self.albedo.value(u, v, rec.point()) * (cosine / PI)
+	}
 
-		// let scatter_direction = rec.point() + Vec3::random_in_hemisphere(rec.normal());
-		// let mut scatter_direction = rec.normal() + Vec3::random_unit_vec();
+	fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+		let cosine = dot(&norm(direction), rec.normal());
+		if cosine > 0.0 {
+			cosine / PI
+		} else {
+			0.0
+		}
+	}
+}
 
-		// if scatter_direction.near_zero() {
-		// 	scatter_direction = *rec.normal();
-		// }
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::math::Point3;
 
-		// Some((
-		// 	self.albedo.clone(),
-		// 	Ray::from(rec.point(), &scatter_direction),
-		// ))
+	#[test]
+	fn test_sample_matches_eval_and_pdf() {
+		let albedo = Color::from(0.2, 0.4, 0.8);
+		let lambertian = Lambertian::from(albedo);
+		let mut rec = HitRecord::new(Arc::new(Lambertian::new()));
+		let r = Ray::from(&Point3::from(0.0, 1.0, 1.0), &Vec3::from(0.0, -1.0, -1.0));
+		rec.calc_normal(&r, Vec3::from(0.0, 1.0, 0.0));
 
-		// my solution
-		Some((
-			self.albedo.clone(),
-			Ray::from(rec.point(), &Vec3::random_in_hemisphere(rec.normal())),
-		))
+		for _ in 0..100 {
+			let sample = lambertian.sample(&r, &rec).unwrap();
+			assert!(!sample.specular);
+			assert!((sample.direction.len() - 1.0).abs() < 1e-9);
+			assert!((sample.pdf - lambertian.pdf(&r, &rec, &sample.direction)).abs() < 1e-12);
+			let weight = lambertian.eval(&r, &rec, &sample.direction) / sample.pdf;
+			assert_eq!(weight.round_to(1_000_000), albedo);
+			assert_eq!(sample.weight, albedo);
+		}
+		let below = Vec3::from(0.3, -1.0, 0.0);
+		assert_eq!(lambertian.pdf(&r, &rec, &below), 0.0);
+		assert_eq!(lambertian.eval(&r, &rec, &below), Color::black());
 	}
 }
@@ -1,43 +1,176 @@
 #![allow(dead_code)]
 use super::HitRecord;
 use crate::{
-	math::{dot, reflect, Ray, Vec3},
+	math::{dot, norm, reflect, Ray, Vec3, PI},
+	sampler::next_2d,
+	scene::{SolidColor, Texture},
 	Color,
 };
+use std::sync::Arc;
 
-use super::Material;
+use super::{BsdfSample, Material};
 
+/// a mirror for a fuzz of zero, otherwise a glossy reflector scattering into a Phong lobe
+/// around the mirror direction which widens with the fuzz
 pub struct Metal {
-	albedo: Color,
+	albedo: Arc<dyn Texture>,
 	fuzz: f64,
 }
+impl Default for Metal {
+	fn default() -> Self {
+		Self::new()
+	}
+}
 
 impl Metal {
 	pub fn new() -> Self {
 		Self {
-			albedo: Color::from(0.0, 0.0, 0.0),
+			albedo: Arc::new(SolidColor::from(Color::from(0.0, 0.0, 0.0))),
 			fuzz: 0.0,
 		}
 	}
 
 	pub fn from(albedo: Color, fuzz: f64) -> Self {
+		Metal::from_texture(Arc::new(SolidColor::from(albedo)), fuzz)
+	}
+
+	pub fn from_texture(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
 		assert!(fuzz <= 1.0);
 		Self { albedo, fuzz }
 	}
 }
 
+impl Metal {
+	/// exponent of the lobe, it spreads about as far as perturbing the mirror direction
+	/// by a random point of a ball with the fuzz as radius
+	fn exponent(&self) -> f64 {
+		(5.0 / (self.fuzz * self.fuzz) - 2.0).max(0.0)
+	}
+
+	/// density of the lobe around the mirror direction of `r_in` towards `direction`,
+	/// zero below the surface
+	fn lobe(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+		let direction = norm(direction);
+		if dot(&direction, rec.normal()) <= 0.0 {
+			return 0.0;
+		}
+		let reflected = norm(&reflect(r_in.direction(), rec.normal()));
+		let cosine = dot(&direction, &reflected);
+		if cosine <= 0.0 {
+			return 0.0;
+		}
+		let exponent = self.exponent();
+		(exponent + 1.0) / (2.0 * PI) * cosine.powf(exponent)
+	}
+}
+
 impl Material for Metal {
-	fn scatter(&self, r_in: &Ray, rec: HitRecord) -> Option<(Color, Ray)> {
-		let reflected = reflect(r_in.direction(), rec.normal());
-		let scattered = Ray::from(
-			rec.point(),
-			&(reflected + self.fuzz * Vec3::random_in_unit_sphere()),
-		);
-		// ommited the check because this can't happen I think
-		if dot(scattered.direction(), rec.normal()) > 0.0 {
-			return Some((self.albedo.clone(), scattered));
+	fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
+		let reflected = norm(&reflect(r_in.direction(), rec.normal()));
+		let (u, v) = rec.uv();
+		let attenuation = self.albedo.value(u, v, rec.point());
+		if self.fuzz == 0.0 {
+			return Some(BsdfSample::specular(reflected, attenuation));
+		}
+		// samples of the lobe below the surface are absorbed, the others keep the albedo
+		// as `eval` is the albedo times the density of the lobe
+		let direction = Vec3::phong_direction(&reflected, self.exponent(), next_2d());
+		let pdf = self.lobe(r_in, rec, &direction);
+		if pdf <= 0.0 {
+			return None;
+		}
+		Some(BsdfSample::from(direction, attenuation, pdf))
+	}
+
+	fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Color {
+		if self.fuzz == 0.0 {
+			return Color::black();
 		}
+		let (u, v) = rec.uv();
+		self.albedo.value(u, v, rec.point()) * self.lobe(r_in, rec, direction)
+	}
 
-		None
+	fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> f64 {
+		if self.fuzz == 0.0 {
+			return 0.0;
+		}
+		self.lobe(r_in, rec, direction)
+	}
+
+	fn is_specular(&self) -> bool {
+		self.fuzz == 0.0
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::math::Point3;
+	use crate::scene::Lambertian;
+
+	fn hit() -> (Ray, HitRecord) {
+		let mut rec = HitRecord::new(Arc::new(Lambertian::new()));
+		let r = Ray::from(&Point3::from(0.0, 1.0, 1.0), &Vec3::from(0.0, -1.0, -1.0));
+		rec.calc_normal(&r, Vec3::from(0.0, 1.0, 0.0));
+		(r, rec)
+	}
+
+	#[test]
+	fn test_sample_matches_eval_and_pdf() {
+		let albedo = Color::from(0.9, 0.6, 0.3);
+		let metal = Metal::from(albedo, 0.5);
+		let (r, rec) = hit();
+		assert!(!metal.is_specular());
+
+		let mut samples = 0;
+		for _ in 0..100 {
+			if let Some(sample) = metal.sample(&r, &rec) {
+				samples += 1;
+				assert!(!sample.specular);
+				assert!((sample.direction.len() - 1.0).abs() < 1e-9);
+				assert!((sample.pdf - metal.pdf(&r, &rec, &sample.direction)).abs() < 1e-12);
+				let weight = metal.eval(&r, &rec, &sample.direction) / sample.pdf;
+				assert_eq!(weight.round_to(1_000_000), albedo);
+				assert_eq!(sample.weight, albedo);
+			}
+		}
+		assert!(samples > 0);
+		let below = Vec3::from(0.0, -1.0, 1.0);
+		assert_eq!(metal.pdf(&r, &rec, &below), 0.0);
+		assert_eq!(metal.eval(&r, &rec, &below), Color::black());
+	}
+
+	#[test]
+	fn test_lobe_integrates_to_one() {
+		// straight down the whole lobe is above the surface, sum it over rings of equal
+		// solid angle around the normal
+		let metal = Metal::from(Color::from(1.0, 1.0, 1.0), 0.5);
+		let mut rec = HitRecord::new(Arc::new(Lambertian::new()));
+		let r = Ray::from(&Point3::from(0.0, 1.0, 0.0), &Vec3::from(0.0, -1.0, 0.0));
+		rec.calc_normal(&r, Vec3::from(0.0, 1.0, 0.0));
+		let rings = 10_000;
+		let total: f64 = (0..rings)
+			.map(|i| {
+				let cosine = (i as f64 + 0.5) / rings as f64;
+				let sine = (1.0 - cosine * cosine).sqrt();
+				let direction = Vec3::from(sine, cosine, 0.0);
+				metal.pdf(&r, &rec, &direction) * 2.0 * PI / rings as f64
+			})
+			.sum();
+		assert!((total - 1.0).abs() < 1e-3, "{}", total);
+	}
+
+	#[test]
+	fn test_mirror_is_a_delta() {
+		let metal = Metal::from(Color::from(0.5, 0.5, 0.5), 0.0);
+		let (r, rec) = hit();
+		assert!(metal.is_specular());
+		let sample = metal.sample(&r, &rec).unwrap();
+		assert!(sample.specular);
+		assert_eq!(
+			sample.direction.round_to(1_000_000),
+			norm(&Vec3::from(0.0, 1.0, -1.0)).round_to(1_000_000)
+		);
+		assert_eq!(metal.pdf(&r, &rec, &sample.direction), 0.0);
 	}
 }
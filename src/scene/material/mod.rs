@@ -1,15 +1,76 @@
 use super::HitRecord;
-use crate::math::Ray;
+use crate::math::{Ray, Vec3};
 use crate::Color;
 
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
 
 mod dielectric;
+mod diffuse_light;
 mod lambertian;
 mod metal;
 
-pub trait Material {
-	fn scatter(&self, r_in: &Ray, rec: HitRecord) -> Option<(Color, Ray)>;
+/// direction a ray continues in after hitting a surface
+pub struct BsdfSample {
+	pub direction: Vec3,
+	/// `eval / pdf` of the direction, the attenuation for specular samples
+	pub weight: Color,
+	/// solid angle density of the direction, zero for specular samples
+	pub pdf: f64,
+	/// the direction was picked from a delta distribution like a mirror reflection,
+	/// neither `eval` nor `pdf` can describe it
+	pub specular: bool,
+}
+
+impl BsdfSample {
+	pub fn from(direction: Vec3, weight: Color, pdf: f64) -> Self {
+		Self {
+			direction,
+			weight,
+			pdf,
+			specular: false,
+		}
+	}
+
+	pub fn specular(direction: Vec3, attenuation: Color) -> Self {
+		Self {
+			direction,
+			weight: attenuation,
+			pdf: 0.0,
+			specular: true,
+		}
+	}
+}
+
+pub trait Material: Send + Sync {
+	/// picks the direction a ray arriving along `r_in` continues in, `None` if it is absorbed
+	fn sample(&self, r_in: &Ray, rec: &HitRecord) -> Option<BsdfSample>;
+
+	/// scattering function times the cosine towards `direction`, the share of light
+	/// arriving from `direction` that leaves the surface along the reversed `r_in`
+	fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> Color {
+		Color::black()
+	}
+
+	/// solid angle density with which `sample` picks `direction`
+	fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: &Vec3) -> f64 {
+		0.0
+	}
+
+	/// whether every sample is specular, light sampling is skipped on such surfaces
+	fn is_specular(&self) -> bool {
+		false
+	}
+
+	/// light given off by the surface, black for everything but light sources
+	fn emitted(&self, _rec: &HitRecord) -> Color {
+		Color::black()
+	}
+
+	/// whether entities made of the material are sampled as light sources
+	fn is_emissive(&self) -> bool {
+		false
+	}
 }
@@ -1,31 +1,132 @@
-use crate::math::Ray;
+use crate::math::{Ray, Vec3};
+use crate::sampler::next_1d;
+use crate::Acceleration;
+use bvh::Bvh;
 pub use entity::hit::*;
+pub use entity::mesh::TriangleMesh;
 pub use entity::sphere::*;
+pub use entity::triangle::Triangle;
 pub use entity::Entity;
-pub use material::{Dielectric, Lambertian, Material, Metal};
-use std::rc::Rc;
+pub use material::{BsdfSample, Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use std::sync::Arc;
+pub use texture::{
+	CheckerTexture, ImageTexture, MarbleTexture, NoiseTexture, Perlin, SolidColor, Texture,
+	UvCheckerTexture, WoodTexture,
+};
 
+mod bvh;
 mod entity;
 mod material;
+mod texture;
 
-use crate::math::rand;
 use crate::math::Point3;
 use crate::Color;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
 
 pub struct Scene {
 	entities: Vec<Entity>,
+	accelerator: Option<Accelerator>,
+	// indices of the entities that emit light
+	lights: Vec<usize>,
+}
+
+struct Accelerator {
+	bvh: Bvh,
+	bounded: Vec<usize>,
+	unbounded: Vec<usize>,
+}
+
+impl Default for Scene {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Scene {
 	pub fn new() -> Self {
-		Self { entities: vec![] }
+		Self {
+			entities: vec![],
+			accelerator: None,
+			lights: vec![],
+		}
+	}
+
+	/// prepares the scene for rendering, has to be called again after adding entities
+	pub fn build(&mut self, acceleration: &Acceleration) {
+		self.accelerator = match acceleration {
+			Acceleration::LINEAR => None,
+			Acceleration::BVH => Some(self.build_bvh()),
+		};
 	}
 
 	pub fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+		match &self.accelerator {
+			Some(accelerator) => self.intersect_bvh(accelerator, r, t_min, t_max),
+			None => self.intersect_linear(r, t_min, t_max),
+		}
+	}
+
+	pub fn add_entity(&mut self, entity: Entity) {
+		if entity.is_light() {
+			self.lights.push(self.entities.len());
+		}
+		self.entities.push(entity);
+		self.accelerator = None;
+	}
+
+	pub fn has_lights(&self) -> bool {
+		!self.lights.is_empty()
+	}
+
+	/// random direction from `origin` towards one of the lights, each light is equally likely
+	pub fn sample_light(&self, origin: &Point3) -> Option<Vec3> {
+		if self.lights.is_empty() {
+			return None;
+		}
+		let i = ((next_1d() * self.lights.len() as f64) as usize).min(self.lights.len() - 1);
+		self.entities[self.lights[i]].sample_direction(origin)
+	}
+
+	/// solid angle density with which `sample_light` yields `direction` from `origin`
+	pub fn light_pdf(&self, origin: &Point3, direction: &Vec3) -> f64 {
+		if self.lights.is_empty() {
+			return 0.0;
+		}
+		let sum: f64 = self
+			.lights
+			.iter()
+			.map(|&i| self.entities[i].pdf_value(origin, direction))
+			.sum();
+		sum / self.lights.len() as f64
+	}
+
+	fn build_bvh(&self) -> Accelerator {
+		let mut bounded = vec![];
+		let mut bounds = vec![];
+		let mut unbounded = vec![];
+		for (i, e) in self.entities.iter().enumerate() {
+			match e.bounding_box() {
+				Some(b) => {
+					bounded.push(i);
+					bounds.push(b);
+				}
+				None => unbounded.push(i),
+			}
+		}
+
+		Accelerator {
+			bvh: Bvh::build(&bounds),
+			bounded,
+			unbounded,
+		}
+	}
+
+	fn intersect_linear(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
 		let mut closest = t_max;
 		let mut result: Option<HitRecord> = None;
 		for e in self.entities.iter() {
-			if let Some(rec) = e.hit(&r, t_min, closest) {
+			if let Some(rec) = e.hit(r, t_min, closest) {
 				closest = rec.t();
 				result = Some(rec);
 			}
@@ -34,17 +135,124 @@ impl Scene {
 		result
 	}
 
-	pub fn add_entity(&mut self, entity: Entity) {
-		self.entities.push(entity);
+	fn intersect_bvh(
+		&self,
+		accelerator: &Accelerator,
+		r: &Ray,
+		t_min: f64,
+		t_max: f64,
+	) -> Option<HitRecord> {
+		let mut closest = t_max;
+		let mut result: Option<HitRecord> = None;
+		for &i in accelerator.unbounded.iter() {
+			if let Some(rec) = self.entities[i].hit(r, t_min, closest) {
+				closest = rec.t();
+				result = Some(rec);
+			}
+		}
+
+		accelerator
+			.bvh
+			.traverse(r, t_min, closest, |i, t_min, t_max| {
+				self.entities[accelerator.bounded[i]].hit(r, t_min, t_max)
+			})
+			.or(result)
 	}
 
-	pub fn random() -> Scene {
+	/// the Cornell box spanning 0 to 555 on every axis, meant to be viewed from
+	/// (278, 278, -800) towards (278, 278, 0) with a 40 degree field of view
+	/// and without a sky
+	pub fn cornell_box() -> Scene {
+		let red: Arc<dyn Material> = Arc::new(Lambertian::from(Color::from(0.65, 0.05, 0.05)));
+		let white: Arc<dyn Material> = Arc::new(Lambertian::from(Color::from(0.73, 0.73, 0.73)));
+		let green: Arc<dyn Material> = Arc::new(Lambertian::from(Color::from(0.12, 0.45, 0.15)));
+		let light: Arc<dyn Material> = Arc::new(DiffuseLight::from(Color::from(15.0, 15.0, 15.0)));
+		let p = Point3::from;
+
+		let mut scene = Scene::new();
+		let walls = [
+			(p(555.0, 0.0, 0.0), p(555.0, 555.0, 555.0), &green),
+			(p(0.0, 0.0, 0.0), p(0.0, 555.0, 555.0), &red),
+			(p(0.0, 0.0, 0.0), p(555.0, 0.0, 555.0), &white),
+			(p(0.0, 555.0, 0.0), p(555.0, 555.0, 555.0), &white),
+			(p(0.0, 0.0, 555.0), p(555.0, 555.0, 555.0), &white),
+			(p(213.0, 554.0, 227.0), p(343.0, 554.0, 332.0), &light),
+		];
+		for (min, max, mat) in walls.iter() {
+			scene.add_entity(Box::new(Scene::rectangle(*min, *max, Arc::clone(mat))));
+		}
+		scene.add_entity(Box::new(Scene::cuboid(
+			p(130.0, 0.0, 65.0),
+			p(295.0, 165.0, 230.0),
+			Arc::clone(&white),
+		)));
+		scene.add_entity(Box::new(Scene::cuboid(
+			p(265.0, 0.0, 295.0),
+			p(430.0, 330.0, 460.0),
+			Arc::clone(&white),
+		)));
+
+		scene
+	}
+
+	/// axis aligned rectangle between two opposite corners that share one coordinate
+	pub(crate) fn rectangle(min: Point3, max: Point3, mat: Arc<dyn Material>) -> TriangleMesh {
+		let axis = (0..3)
+			.find(|&a| min[a] == max[a])
+			.expect("rectangle corners have to share one coordinate");
+		let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+		let corner = |a: f64, b: f64| {
+			let mut p = min;
+			p[u] = a;
+			p[v] = b;
+			p
+		};
+		let positions = vec![
+			corner(min[u], min[v]),
+			corner(max[u], min[v]),
+			corner(max[u], max[v]),
+			corner(min[u], max[v]),
+		];
+		TriangleMesh::from(positions, vec![[0, 1, 2], [0, 2, 3]], mat)
+	}
+
+	/// axis aligned box between two opposite corners
+	pub(crate) fn cuboid(min: Point3, max: Point3, mat: Arc<dyn Material>) -> TriangleMesh {
+		let corner = |i: usize| {
+			Point3::from(
+				if i & 1 == 0 { min.x() } else { max.x() },
+				if i & 2 == 0 { min.y() } else { max.y() },
+				if i & 4 == 0 { min.z() } else { max.z() },
+			)
+		};
+		let positions = (0..8).map(corner).collect();
+		let faces = vec![
+			[0, 2, 3],
+			[0, 3, 1],
+			[4, 5, 7],
+			[4, 7, 6],
+			[0, 4, 6],
+			[0, 6, 2],
+			[1, 3, 7],
+			[1, 7, 5],
+			[0, 1, 5],
+			[0, 5, 4],
+			[2, 6, 7],
+			[2, 7, 3],
+		];
+		TriangleMesh::from(positions, faces, mat)
+	}
+
+	/// the final scene of the first book, the same seed always yields the same spheres
+	pub fn random(seed: u64) -> Scene {
+		let mut rng = Pcg64Mcg::seed_from_u64(seed);
+		let mut rand = || rng.gen_range(0.0..=1.0);
 		let mut entities: Vec<Entity> = vec![];
 		let ground_mat = Lambertian::from(Color::from(0.5, 0.5, 0.5));
 		let ground = Box::new(Sphere::from(
 			Point3::from(0.0, -1000.0, 0.0),
 			1000.0,
-			Rc::new(ground_mat),
+			Arc::new(ground_mat),
 		));
 		entities.push(ground);
 
@@ -56,19 +264,20 @@ impl Scene {
 				if (center - Point3::from(4.0, 0.2, 0.0)).len() > 0.9 {
 					if choose_mat < 0.8 {
 						// diffuse
-						let albedo = Color::from_rand() * Color::from_rand();
+						let albedo = Color::from(rand() * rand(), rand() * rand(), rand() * rand());
 						let mat = Lambertian::from(albedo);
-						entities.push(Box::new(Sphere::from(center, 0.2, Rc::new(mat))));
+						entities.push(Box::new(Sphere::from(center, 0.2, Arc::new(mat))));
 					} else if choose_mat < 0.95 {
 						// metal
-						let albedo = Color::from_rand_rng(0.5, 1.0);
+						let albedo =
+							Color::from(rand(), rand(), rand()) * 0.5 + Color::from(0.5, 0.5, 0.5);
 						let fuzz = rand();
 						let mat = Metal::from(albedo, fuzz);
-						entities.push(Box::new(Sphere::from(center, 0.2, Rc::new(mat))))
+						entities.push(Box::new(Sphere::from(center, 0.2, Arc::new(mat))))
 					} else {
 						// glass
 						let mat = Dielectric::from(1.5);
-						entities.push(Box::new(Sphere::from(center, 0.2, Rc::new(mat))))
+						entities.push(Box::new(Sphere::from(center, 0.2, Arc::new(mat))))
 					}
 				}
 			}
@@ -78,35 +287,39 @@ impl Scene {
 		entities.push(Box::new(Sphere::from(
 			Point3::from(0.0, 1.0, 0.0),
 			1.0,
-			Rc::new(mat),
+			Arc::new(mat),
 		)));
 		let mat = Lambertian::from(Color::from(0.4, 0.2, 0.1));
 		entities.push(Box::new(Sphere::from(
 			Point3::from(-4.0, 1.0, 0.0),
 			1.0,
-			Rc::new(mat),
+			Arc::new(mat),
 		)));
 		let mat = Metal::from(Color::from(0.7, 0.6, 0.5), 0.0);
 		entities.push(Box::new(Sphere::from(
 			Point3::from(4.0, 1.0, 0.0),
 			1.0,
-			Rc::new(mat),
+			Arc::new(mat),
 		)));
 
-		Self { entities }
+		Self {
+			entities,
+			accelerator: None,
+			lights: vec![],
+		}
 	}
 }
 
 #[cfg(test)]
 mod test {
-	use crate::math::norm;
+	use crate::math::{norm, rand, Vec3, INFINITY};
 
 	use super::*;
 
 	#[test]
 	fn test_intersect() {
 		let mut scene = Scene::new();
-		let red_diffuse = Rc::new(Lambertian::from(Color::from(1.0, 0.0, 0.0)));
+		let red_diffuse = Arc::new(Lambertian::from(Color::from(1.0, 0.0, 0.0)));
 
 		let far_away_sphere = Box::new(Sphere::from(
 			Point3::from(0.0, 0.0, -10.0),
@@ -139,4 +352,100 @@ mod test {
 			panic!("No intersection found");
 		}
 	}
+
+	#[test]
+	fn test_scene_is_shareable_between_threads() {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<Scene>();
+		assert_send_sync::<HitRecord>();
+	}
+
+	#[test]
+	fn test_random_is_reproducible() {
+		let scenes = [Scene::random(7), Scene::random(7), Scene::random(8)];
+		let origin = Point3::from(13.0, 2.0, 3.0);
+		let distances = |scene: &Scene| -> Vec<Option<f64>> {
+			(0..100)
+				.map(|i| {
+					let target = Point3::from(i as f64 * 0.2 - 10.0, 0.2, (i % 7) as f64 - 3.0);
+					let r = Ray::from(&origin, &(target - origin));
+					scene.intersect(&r, 0.001, INFINITY).map(|rec| rec.t())
+				})
+				.collect()
+		};
+
+		assert_eq!(scenes[0].entities.len(), scenes[1].entities.len());
+		assert_eq!(distances(&scenes[0]), distances(&scenes[1]));
+		assert_ne!(distances(&scenes[0]), distances(&scenes[2]));
+	}
+
+	#[test]
+	fn test_bvh_matches_linear() {
+		let mut scene = Scene::random(0);
+		let origin = Point3::from(13.0, 2.0, 3.0);
+		let rays: Vec<Ray> = (0..500)
+			.map(|_| {
+				let target = Point3::from(
+					rand() * 20.0 - 10.0,
+					rand() * 2.0 - 0.5,
+					rand() * 20.0 - 10.0,
+				);
+				Ray::from(&origin, &(target - origin))
+			})
+			.collect();
+
+		scene.build(&Acceleration::LINEAR);
+		let linear: Vec<Option<f64>> = rays
+			.iter()
+			.map(|r| scene.intersect(r, 0.001, INFINITY).map(|rec| rec.t()))
+			.collect();
+
+		scene.build(&Acceleration::BVH);
+		let accelerated: Vec<Option<f64>> = rays
+			.iter()
+			.map(|r| scene.intersect(r, 0.001, INFINITY).map(|rec| rec.t()))
+			.collect();
+
+		assert_eq!(linear, accelerated);
+	}
+
+	#[test]
+	fn test_lights() {
+		let mut scene = Scene::cornell_box();
+		assert_eq!(scene.lights.len(), 1);
+		assert!(!Scene::random(0).has_lights());
+
+		let origin = Point3::from(278.0, 278.0, 278.0);
+		for _ in 0..100 {
+			let direction = scene.sample_light(&origin).unwrap();
+			let r = Ray::from(&origin, &direction);
+			let rec = scene.intersect(&r, 0.001, INFINITY).unwrap();
+			assert!((rec.point().y() - 554.0).abs() < 1e-6);
+			assert!(scene.light_pdf(&origin, &direction) > 0.0);
+		}
+		assert_eq!(scene.light_pdf(&origin, &Vec3::from(0.0, -1.0, 0.0)), 0.0);
+
+		scene.add_entity(Box::new(Sphere::from(
+			Point3::new(),
+			1.0,
+			Arc::new(DiffuseLight::from(Color::from(1.0, 1.0, 1.0))),
+		)));
+		assert_eq!(scene.lights, vec![5, 8]);
+	}
+
+	#[test]
+	fn test_cornell_box_is_closed_except_front() {
+		let mut scene = Scene::cornell_box();
+		scene.build(&Acceleration::BVH);
+		let center = Point3::from(278.0, 400.0, 278.0);
+
+		for _ in 0..200 {
+			// the camera looks in through the open side facing negative z
+			let r = Ray::from(
+				&center,
+				&Vec3::random_in_hemisphere(&Vec3::from(0.0, 0.0, 1.0)),
+			);
+			assert!(scene.intersect(&r, 0.001, INFINITY).is_some());
+		}
+	}
 }
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use super::{SolidColor, Texture};
+use crate::math::Point3;
+use crate::Color;
+
+/// checkerboard of cubes with edge length `scale` in world space
+pub struct CheckerTexture {
+	inv_scale: f64,
+	even: Arc<dyn Texture>,
+	odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+	pub fn from(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+		Self {
+			inv_scale: 1.0 / scale,
+			even,
+			odd,
+		}
+	}
+
+	pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+		CheckerTexture::from(
+			scale,
+			Arc::new(SolidColor::from(even)),
+			Arc::new(SolidColor::from(odd)),
+		)
+	}
+}
+
+impl Texture for CheckerTexture {
+	fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+		let cell = (self.inv_scale * p.x()).floor()
+			+ (self.inv_scale * p.y()).floor()
+			+ (self.inv_scale * p.z()).floor();
+		if cell as i64 % 2 == 0 {
+			self.even.value(u, v, p)
+		} else {
+			self.odd.value(u, v, p)
+		}
+	}
+}
+
+/// checkerboard with `columns` by `rows` squares stretched over the surface coordinates
+pub struct UvCheckerTexture {
+	columns: f64,
+	rows: f64,
+	even: Arc<dyn Texture>,
+	odd: Arc<dyn Texture>,
+}
+
+impl UvCheckerTexture {
+	pub fn from(columns: u32, rows: u32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+		Self {
+			columns: columns as f64,
+			rows: rows as f64,
+			even,
+			odd,
+		}
+	}
+
+	pub fn from_colors(columns: u32, rows: u32, even: Color, odd: Color) -> Self {
+		UvCheckerTexture::from(
+			columns,
+			rows,
+			Arc::new(SolidColor::from(even)),
+			Arc::new(SolidColor::from(odd)),
+		)
+	}
+}
+
+impl Texture for UvCheckerTexture {
+	fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+		let cell = (u * self.columns).floor() + (v * self.rows).floor();
+		if cell as i64 % 2 == 0 {
+			self.even.value(u, v, p)
+		} else {
+			self.odd.value(u, v, p)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_checker_alternates_in_space() {
+		let white = Color::from(1.0, 1.0, 1.0);
+		let black = Color::black();
+		let checker = CheckerTexture::from_colors(1.0, white, black);
+
+		assert_eq!(checker.value(0.0, 0.0, &Point3::from(0.5, 0.5, 0.5)), white);
+		assert_eq!(checker.value(0.0, 0.0, &Point3::from(1.5, 0.5, 0.5)), black);
+		assert_eq!(
+			checker.value(0.0, 0.0, &Point3::from(-0.5, 0.5, 0.5)),
+			black
+		);
+		assert_eq!(
+			checker.value(0.0, 0.0, &Point3::from(-0.5, -0.5, 0.5)),
+			white
+		);
+	}
+
+	#[test]
+	fn test_uv_checker_alternates_on_surface() {
+		let white = Color::from(1.0, 1.0, 1.0);
+		let black = Color::black();
+		let checker = UvCheckerTexture::from_colors(4, 2, white, black);
+		let p = Point3::new();
+
+		assert_eq!(checker.value(0.1, 0.1, &p), white);
+		assert_eq!(checker.value(0.3, 0.1, &p), black);
+		assert_eq!(checker.value(0.3, 0.6, &p), white);
+	}
+}
@@ -0,0 +1,95 @@
+use super::Texture;
+use crate::color::srgb_decode;
+use crate::loader::{load_ppm, PpmError};
+use crate::math::Point3;
+use crate::Color;
+
+/// texture backed by a grid of linear colors, sampled with the nearest pixel; it repeats
+/// for texture coordinates outside [0, 1], as meshes use them to tile a texture
+pub struct ImageTexture {
+	width: usize,
+	height: usize,
+	pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+	/// `pixels` are linear colors stored row by row starting at the top
+	pub fn from(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+		assert_eq!(pixels.len(), width * height);
+		Self {
+			width,
+			height,
+			pixels,
+		}
+	}
+
+	/// loads an sRGB encoded PPM image as written by `PPMWriter`
+	pub fn load(path: &str) -> Result<Self, PpmError> {
+		let img = load_ppm(path)?;
+		let max = img.max_color as f64;
+		let pixels = img
+			.data
+			.iter()
+			.map(|[r, g, b]| {
+				Color::from(
+					srgb_decode(*r as f64 / max),
+					srgb_decode(*g as f64 / max),
+					srgb_decode(*b as f64 / max),
+				)
+			})
+			.collect();
+		Ok(ImageTexture::from(img.width, img.height, pixels))
+	}
+}
+
+impl Texture for ImageTexture {
+	fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+		if self.pixels.is_empty() {
+			// make missing images stand out
+			return Color::from(0.0, 1.0, 1.0);
+		}
+		let u = wrap(u);
+		// image rows start at the top while v grows upwards
+		let v = 1.0 - wrap(v);
+		let i = ((u * self.width as f64) as usize).min(self.width - 1);
+		let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+		self.pixels[j * self.width + i]
+	}
+}
+
+/// the fraction of `x` for coordinates outside [0, 1], 1 itself stays the far edge
+fn wrap(x: f64) -> f64 {
+	if (0.0..=1.0).contains(&x) {
+		x
+	} else {
+		x - x.floor()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_lookup_corners() {
+		let red = Color::from(1.0, 0.0, 0.0);
+		let green = Color::from(0.0, 1.0, 0.0);
+		let blue = Color::from(0.0, 0.0, 1.0);
+		let white = Color::from(1.0, 1.0, 1.0);
+		let texture = ImageTexture::from(2, 2, vec![red, green, blue, white]);
+		let p = Point3::new();
+
+		assert_eq!(texture.value(0.0, 1.0, &p), red);
+		assert_eq!(texture.value(1.0, 1.0, &p), green);
+		assert_eq!(texture.value(0.0, 0.0, &p), blue);
+		assert_eq!(texture.value(0.9, 0.1, &p), white);
+		assert_eq!(texture.value(1.25, -0.75, &p), blue);
+		assert_eq!(texture.value(-0.25, 2.75, &p), green);
+	}
+
+	#[test]
+	fn test_load_missing_file() {
+		assert!(ImageTexture::load("./assets/does-not-exist.ppm").is_err());
+	}
+}
@@ -0,0 +1,19 @@
+use crate::math::Point3;
+use crate::Color;
+
+pub use checker::{CheckerTexture, UvCheckerTexture};
+pub use image::ImageTexture;
+pub use noise::{MarbleTexture, NoiseTexture, WoodTexture};
+pub use perlin::Perlin;
+pub use solid::SolidColor;
+
+mod checker;
+mod image;
+mod noise;
+mod perlin;
+mod solid;
+
+/// color lookup by surface coordinates (u, v) and the hit point
+pub trait Texture: Send + Sync {
+	fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
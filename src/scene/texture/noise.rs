@@ -0,0 +1,125 @@
+use super::perlin::Perlin;
+use super::Texture;
+use crate::math::Point3;
+use crate::Color;
+
+const TURBULENCE_DEPTH: u32 = 7;
+
+/// blends between two colors by plain perlin noise
+pub struct NoiseTexture {
+	noise: Perlin,
+	scale: f64,
+	low: Color,
+	high: Color,
+}
+
+impl NoiseTexture {
+	pub fn from(seed: u64, scale: f64, low: Color, high: Color) -> Self {
+		Self {
+			noise: Perlin::from_seed(seed),
+			scale,
+			low,
+			high,
+		}
+	}
+}
+
+impl Texture for NoiseTexture {
+	fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+		let t = 0.5 * (1.0 + self.noise.noise(&(*p * self.scale)));
+		self.low * (1.0 - t) + self.high * t
+	}
+}
+
+/// veins running along the z-axis, distorted by turbulence
+pub struct MarbleTexture {
+	noise: Perlin,
+	scale: f64,
+	base: Color,
+	vein: Color,
+}
+
+impl MarbleTexture {
+	pub fn from(seed: u64, scale: f64, base: Color, vein: Color) -> Self {
+		Self {
+			noise: Perlin::from_seed(seed),
+			scale,
+			base,
+			vein,
+		}
+	}
+}
+
+impl Texture for MarbleTexture {
+	fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+		let phase = self.scale * p.z() + 10.0 * self.noise.turbulence(p, TURBULENCE_DEPTH);
+		let t = 0.5 * (1.0 + phase.sin());
+		self.vein * (1.0 - t) + self.base * t
+	}
+}
+
+/// growth rings around the y-axis, wobbled by turbulence
+pub struct WoodTexture {
+	noise: Perlin,
+	rings_per_unit: f64,
+	light: Color,
+	dark: Color,
+}
+
+impl WoodTexture {
+	pub fn from(seed: u64, rings_per_unit: f64, light: Color, dark: Color) -> Self {
+		Self {
+			noise: Perlin::from_seed(seed),
+			rings_per_unit,
+			light,
+			dark,
+		}
+	}
+}
+
+impl Texture for WoodTexture {
+	fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+		let radius = (p.x() * p.x() + p.z() * p.z()).sqrt();
+		let rings = radius * self.rings_per_unit + 2.0 * self.noise.turbulence(p, TURBULENCE_DEPTH);
+		let t = rings - rings.floor();
+		// sharpen the transition so that rings get a dark edge
+		let t = t * t;
+		self.light * (1.0 - t) + self.dark * t
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn in_between(c: Color, a: Color, b: Color) -> bool {
+		(0..3).all(|i| c[i] >= a[i].min(b[i]) - 1e-12 && c[i] <= a[i].max(b[i]) + 1e-12)
+	}
+
+	#[test]
+	fn test_textures_blend_their_colors() {
+		let white = Color::from(1.0, 1.0, 1.0);
+		let brown = Color::from(0.4, 0.2, 0.1);
+		let textures: Vec<Box<dyn Texture>> = vec![
+			Box::new(NoiseTexture::from(1, 4.0, brown, white)),
+			Box::new(MarbleTexture::from(1, 4.0, white, brown)),
+			Box::new(WoodTexture::from(1, 8.0, white, brown)),
+		];
+
+		for texture in textures.iter() {
+			for i in 0..100 {
+				let f = i as f64 * 0.173;
+				let c = texture.value(0.0, 0.0, &Point3::from(f, -f * 0.7, f * 1.3));
+				assert!(in_between(c, white, brown));
+			}
+		}
+	}
+
+	#[test]
+	fn test_seeded_textures_are_reproducible() {
+		let p = Point3::from(0.3, 1.7, -2.2);
+		let a = MarbleTexture::from(42, 4.0, Color::from(1.0, 1.0, 1.0), Color::black());
+		let b = MarbleTexture::from(42, 4.0, Color::from(1.0, 1.0, 1.0), Color::black());
+		assert_eq!(a.value(0.0, 0.0, &p), b.value(0.0, 0.0, &p));
+	}
+}
@@ -0,0 +1,151 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+use crate::math::{dot, norm, Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// gradient noise after Ken Perlin, the same seed always yields the same noise
+pub struct Perlin {
+	gradients: Vec<Vec3>,
+	perm_x: Vec<usize>,
+	perm_y: Vec<usize>,
+	perm_z: Vec<usize>,
+}
+
+impl Perlin {
+	pub fn from_seed(seed: u64) -> Self {
+		let mut rng = Pcg64Mcg::seed_from_u64(seed);
+		let gradients = (0..POINT_COUNT)
+			.map(|_| loop {
+				let v = Vec3::from(
+					rng.gen_range(-1.0..=1.0),
+					rng.gen_range(-1.0..=1.0),
+					rng.gen_range(-1.0..=1.0),
+				);
+				let len_squared = v.len_squared();
+				if len_squared > 1e-6 && len_squared <= 1.0 {
+					break norm(&v);
+				}
+			})
+			.collect();
+
+		Self {
+			gradients,
+			perm_x: Perlin::permutation(&mut rng),
+			perm_y: Perlin::permutation(&mut rng),
+			perm_z: Perlin::permutation(&mut rng),
+		}
+	}
+
+	/// smooth noise in [-1, 1]
+	pub fn noise(&self, p: &Point3) -> f64 {
+		let (u, v, w) = (
+			p.x() - p.x().floor(),
+			p.y() - p.y().floor(),
+			p.z() - p.z().floor(),
+		);
+		let (i, j, k) = (
+			p.x().floor() as i64,
+			p.y().floor() as i64,
+			p.z().floor() as i64,
+		);
+
+		let mut corners = [[[Vec3::new(); 2]; 2]; 2];
+		for (di, plane) in corners.iter_mut().enumerate() {
+			for (dj, row) in plane.iter_mut().enumerate() {
+				for (dk, corner) in row.iter_mut().enumerate() {
+					*corner = self.gradients[self.perm_x[Perlin::wrap(i + di as i64)]
+						^ self.perm_y[Perlin::wrap(j + dj as i64)]
+						^ self.perm_z[Perlin::wrap(k + dk as i64)]];
+				}
+			}
+		}
+
+		Perlin::interpolate(&corners, u, v, w)
+	}
+
+	/// sum of `depth` octaves of noise with halving weights, always positive
+	pub fn turbulence(&self, p: &Point3, depth: u32) -> f64 {
+		let mut acc = 0.0;
+		let mut p = *p;
+		let mut weight = 1.0;
+		for _ in 0..depth {
+			acc += weight * self.noise(&p);
+			weight *= 0.5;
+			p = p * 2.0;
+		}
+		acc.abs()
+	}
+
+	fn permutation(rng: &mut Pcg64Mcg) -> Vec<usize> {
+		let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+		p.shuffle(rng);
+		p
+	}
+
+	fn wrap(i: i64) -> usize {
+		(i & (POINT_COUNT as i64 - 1)) as usize
+	}
+
+	fn interpolate(corners: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+		// hermite smoothing hides the grid structure
+		let uu = u * u * (3.0 - 2.0 * u);
+		let vv = v * v * (3.0 - 2.0 * v);
+		let ww = w * w * (3.0 - 2.0 * w);
+
+		let mut acc = 0.0;
+		for (i, plane) in corners.iter().enumerate() {
+			for (j, row) in plane.iter().enumerate() {
+				for (k, gradient) in row.iter().enumerate() {
+					let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+					let weight = Vec3::from(u - fi, v - fj, w - fk);
+					acc += (fi * uu + (1.0 - fi) * (1.0 - uu))
+						* (fj * vv + (1.0 - fj) * (1.0 - vv))
+						* (fk * ww + (1.0 - fk) * (1.0 - ww))
+						* dot(gradient, &weight);
+				}
+			}
+		}
+		acc
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_same_seed_same_noise() {
+		let a = Perlin::from_seed(7);
+		let b = Perlin::from_seed(7);
+		let c = Perlin::from_seed(8);
+		let p = Point3::from(1.3, -2.7, 0.42);
+
+		assert_eq!(a.noise(&p), b.noise(&p));
+		assert_ne!(a.noise(&p), c.noise(&p));
+	}
+
+	#[test]
+	fn test_noise_range_and_lattice() {
+		let perlin = Perlin::from_seed(1);
+		// gradient noise vanishes on the integer lattice
+		assert_eq!(perlin.noise(&Point3::from(3.0, -1.0, 2.0)), 0.0);
+
+		for i in 0..1000 {
+			let f = i as f64 * 0.137;
+			let n = perlin.noise(&Point3::from(f, f * 0.5, -f * 0.3));
+			assert!((-1.0..=1.0).contains(&n));
+		}
+	}
+
+	#[test]
+	fn test_turbulence_is_positive() {
+		let perlin = Perlin::from_seed(3);
+		for i in 0..100 {
+			let f = i as f64 * 0.31;
+			assert!(perlin.turbulence(&Point3::from(f, -f, f * 2.0), 7) >= 0.0);
+		}
+	}
+}
@@ -0,0 +1,19 @@
+use super::Texture;
+use crate::math::Point3;
+use crate::Color;
+
+pub struct SolidColor {
+	color: Color,
+}
+
+impl SolidColor {
+	pub fn from(color: Color) -> Self {
+		Self { color }
+	}
+}
+
+impl Texture for SolidColor {
+	fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+		self.color
+	}
+}
@@ -1,36 +1,183 @@
 use crate::math::Point3;
+use crate::Color;
 use std::default::Default;
+use std::time::Duration;
 
+#[derive(Debug)]
 pub enum Antialiasing {
-	MSAA { samples_per_pixel: i32 },
+	MSAA {
+		samples_per_pixel: i32,
+	},
+	/// samples every pixel until the standard error of its mean luminance relative to
+	/// the mean drops to `threshold`, taking between `min_samples` and `max_samples`
+	ADAPTIVE {
+		min_samples: i32,
+		max_samples: i32,
+		threshold: f64,
+	},
 	NONE,
 }
+#[derive(Debug)]
+pub enum Acceleration {
+	BVH,
+	LINEAR,
+}
+
+/// what rays that leave the scene see
+#[derive(Debug)]
+pub enum Background {
+	SKY,
+	SOLID { color: Color },
+}
+
+/// auxiliary layer, traced with a single ray through every pixel center unless noted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+	/// attenuation of the first surface hit
+	ALBEDO,
+	/// world space normal facing against the ray
+	NORMAL,
+	/// distance from the camera to the first surface hit
+	DEPTH,
+	/// number of samples taken for the pixel, recorded while rendering the image
+	SAMPLES,
+}
+
+/// operator compressing linear radiance into the displayable range
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMapping {
+	/// clamps every channel to 1
+	LINEAR,
+	/// `L / (1 + L)` on luminance
+	REINHARD,
+	/// Reinhard mapping luminance `white_point` and above to 1, the white point has to be
+	/// positive and falls back to plain Reinhard otherwise
+	EXTENDED_REINHARD { white_point: f64 },
+	/// Narkowicz' fit of the ACES filmic curve
+	ACES,
+	/// Hable's filmic curve from Uncharted 2
+	UNCHARTED2,
+}
+
+/// how low dynamic range outputs present the linear framebuffer
+#[derive(Debug, Clone, Copy)]
+pub struct DisplaySettings {
+	pub tone_mapping: ToneMapping,
+	/// exposure adjustment in stops applied before tone mapping
+	pub exposure: f64,
+}
+
+/// how the random decisions of the samples of a pixel are spread
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+	/// independent random numbers
+	RANDOM,
+	/// one jittered sample per stratum of a shuffled grid
+	STRATIFIED,
+	/// the Halton sequence shifted per pixel
+	HALTON,
+	/// Owen scrambled Sobol points
+	SOBOL,
+	/// correlated multi-jittered sampling
+	CMJ,
+}
+
+/// reconstruction filter weighting the samples around every pixel center, `radius` is
+/// measured in pixels and every sample is splatted onto all pixels within it
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+	/// equal weights, a radius of 0.5 keeps every sample inside its pixel
+	BOX { radius: f64 },
+	/// weights falling linearly to 0 at the radius
+	TENT { radius: f64 },
+	/// `e^(-alpha x^2)` shifted to reach 0 at the radius
+	GAUSSIAN { radius: f64, alpha: f64 },
+	/// Mitchell-Netravali cubic, `b = c = 1/3` is the recommended balance of blur and ringing
+	MITCHELL { radius: f64, b: f64, c: f64 },
+	/// sinc windowed by a sinc stretched to the radius
+	LANCZOS { radius: f64 },
+	/// four term Blackman-Harris window
+	BLACKMAN_HARRIS { radius: f64 },
+}
+
+/// limits of `Renderer::render_progressive`, which renders one sample of every pixel
+/// per pass up to the samples per pixel of the antialiasing and stops early at the
+/// first limit reached
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Progressive {
+	/// time after which no further pass is started
+	pub time_limit: Option<Duration>,
+	/// mean relative standard error of the pixels at which the image counts as converged
+	pub noise_target: Option<f64>,
+	/// least time between two previews, `None` writes one after every pass
+	pub preview_interval: Option<Duration>,
+	/// file the state of the render is saved to, see `Checkpoint`
+	pub checkpoint: Option<String>,
+	/// least time between two checkpoints, `None` saves one after every pass
+	pub checkpoint_interval: Option<Duration>,
+	/// identifies the scene in checkpoints, such as a hash of its source, as the settings
+	/// do not; a render only resumes a checkpoint of the same scene
+	pub scene: String,
+}
+
+#[derive(Debug)]
 pub struct ImageSettings {
 	pub width: i32,
 	pub height: i32,
 }
 
+#[derive(Debug)]
 pub enum DefocusBlur {
 	ON { focus_distance: f64, aperture: f64 },
 	OFF,
 }
 
+/// ends paths at random once little of their light can reach the camera, the paths
+/// that go on are weighted up by the survival probability to keep the image unbiased
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RussianRoulette {
+	ON {
+		/// number of bounces every path makes before it can be terminated
+		min_depth: i32,
+	},
+	OFF,
+}
+
+#[derive(Debug)]
 pub struct RenderSettings {
 	pub max_depth: i32,
+	pub russian_roulette: RussianRoulette,
 	pub antialiasing: Antialiasing,
+	pub filter: Filter,
+	/// sample points used for the pixel position, the lens and every bounce
+	pub sampling: Sampling,
 	pub blur: DefocusBlur,
+	pub acceleration: Acceleration,
+	pub background: Background,
+	/// number of worker threads, 0 uses all available cores
+	pub threads: usize,
+	/// layers rendered next to the image, written by writers that support layers
+	pub aovs: Vec<Aov>,
+	/// every sample draws its random numbers from this seed and its pixel and sample index,
+	/// so the same seed renders the same image on any number of threads
+	pub seed: u64,
 }
 
+#[derive(Debug)]
 pub struct CameraSettings {
 	pub look_at: Point3,
 	pub look_from: Point3,
 	pub field_of_view: f64,
 }
 
+#[derive(Debug, Default)]
 pub struct Settings {
 	pub rendering: RenderSettings,
 	pub camera: CameraSettings,
 	pub image: ImageSettings,
+	pub display: DisplaySettings,
 }
 
 impl Settings {
@@ -46,26 +193,74 @@ impl Settings {
 		&self.rendering.antialiasing
 	}
 
+	pub fn filter(&self) -> &Filter {
+		&self.rendering.filter
+	}
+
+	pub fn sampling(&self) -> &Sampling {
+		&self.rendering.sampling
+	}
+
 	pub fn max_depth(&self) -> i32 {
 		self.rendering.max_depth
 	}
 
+	pub fn russian_roulette(&self) -> &RussianRoulette {
+		&self.rendering.russian_roulette
+	}
+
 	pub fn defocus_blur(&self) -> &DefocusBlur {
 		&self.rendering.blur
 	}
+
+	pub fn acceleration(&self) -> &Acceleration {
+		&self.rendering.acceleration
+	}
+
+	pub fn background(&self) -> &Background {
+		&self.rendering.background
+	}
+
+	pub fn threads(&self) -> usize {
+		self.rendering.threads
+	}
+
+	pub fn aovs(&self) -> &[Aov] {
+		&self.rendering.aovs
+	}
+
+	pub fn seed(&self) -> u64 {
+		self.rendering.seed
+	}
+
+	pub fn tone_mapping(&self) -> &ToneMapping {
+		&self.display.tone_mapping
+	}
+
+	pub fn exposure(&self) -> f64 {
+		self.display.exposure
+	}
 }
 
 impl Default for RenderSettings {
 	fn default() -> Self {
 		Self {
 			max_depth: 50,
+			russian_roulette: RussianRoulette::OFF,
 			antialiasing: Antialiasing::MSAA {
 				samples_per_pixel: 50,
 			},
+			filter: Filter::BOX { radius: 0.5 },
+			sampling: Sampling::RANDOM,
 			blur: DefocusBlur::ON {
 				focus_distance: 10.0,
 				aperture: 0.1,
 			},
+			acceleration: Acceleration::BVH,
+			background: Background::SKY,
+			threads: 0,
+			aovs: vec![],
+			seed: 0,
 		}
 	}
 }
@@ -80,21 +275,20 @@ impl Default for CameraSettings {
 	}
 }
 
-impl Default for ImageSettings {
+impl Default for DisplaySettings {
 	fn default() -> Self {
 		Self {
-			width: 720,
-			height: 576,
+			tone_mapping: ToneMapping::LINEAR,
+			exposure: 0.0,
 		}
 	}
 }
 
-impl Default for Settings {
+impl Default for ImageSettings {
 	fn default() -> Self {
 		Self {
-			rendering: Default::default(),
-			camera: Default::default(),
-			image: Default::default(),
+			width: 720,
+			height: 576,
 		}
 	}
 }
@@ -0,0 +1,125 @@
+use crate::settings::{DisplaySettings, ToneMapping};
+use crate::Color;
+
+impl DisplaySettings {
+	/// scales the color by the exposure and tone maps it, the result still has to be encoded
+	pub fn apply(&self, color: Color) -> Color {
+		self.tone_mapping.map(color * 2f64.powf(self.exposure))
+	}
+}
+
+impl ToneMapping {
+	pub fn map(&self, color: Color) -> Color {
+		match *self {
+			ToneMapping::LINEAR => color,
+			ToneMapping::REINHARD => reinhard(color, f64::INFINITY),
+			ToneMapping::EXTENDED_REINHARD { white_point } => reinhard(color, white_point),
+			ToneMapping::ACES => per_channel(color, aces),
+			ToneMapping::UNCHARTED2 => {
+				let white_scale = 1.0 / hable(UNCHARTED2_WHITE);
+				per_channel(color, |c| hable(UNCHARTED2_EXPOSURE_BIAS * c) * white_scale)
+			}
+		}
+	}
+}
+
+const UNCHARTED2_WHITE: f64 = 11.2;
+const UNCHARTED2_EXPOSURE_BIAS: f64 = 2.0;
+
+fn per_channel(color: Color, f: impl Fn(f64) -> f64) -> Color {
+	Color::from(f(color.x()), f(color.y()), f(color.z()))
+}
+
+// scales the color so its luminance follows `L (1 + L / white^2) / (1 + L)`, keeping the hue
+fn reinhard(color: Color, white_point: f64) -> Color {
+	let l = color.luminance();
+	if l <= 0.0 {
+		return Color::black();
+	}
+	// a white point of zero would divide by zero, it is taken as no white point at all
+	let white_point = if white_point > 0.0 {
+		white_point
+	} else {
+		f64::INFINITY
+	};
+	let mapped = l * (1.0 + l / (white_point * white_point)) / (1.0 + l);
+	color * (mapped / l)
+}
+
+fn aces(x: f64) -> f64 {
+	let x = x.max(0.0);
+	(x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+fn hable(x: f64) -> f64 {
+	let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+	((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn grey(v: f64) -> Color {
+		Color::from(v, v, v)
+	}
+
+	#[test]
+	fn test_operators_are_monotonic() {
+		let operators = [
+			ToneMapping::REINHARD,
+			ToneMapping::EXTENDED_REINHARD { white_point: 4.0 },
+			ToneMapping::ACES,
+			ToneMapping::UNCHARTED2,
+		];
+		for op in operators.iter() {
+			assert!(op.map(Color::black()).x().abs() < 1e-9);
+			let mut previous = 0.0;
+			for i in 1..200 {
+				let mapped = op.map(grey(i as f64 * 0.05)).x();
+				assert!(mapped > previous, "{:?} is not increasing", op);
+				previous = mapped;
+			}
+		}
+		// the extended Reinhard and Uncharted 2 curves pass 1 beyond their white points
+		for op in [ToneMapping::REINHARD, ToneMapping::ACES] {
+			assert!(op.map(grey(1000.0)).x() < 1.05, "{:?} is unbounded", op);
+		}
+	}
+
+	#[test]
+	fn test_white_points() {
+		let extended = ToneMapping::EXTENDED_REINHARD { white_point: 4.0 };
+		assert!((extended.map(grey(4.0)).x() - 1.0).abs() < 1e-12);
+		assert!((ToneMapping::REINHARD.map(grey(1.0)).x() - 0.5).abs() < 1e-12);
+		for white_point in [0.0, -1.0, f64::NAN].iter() {
+			let invalid = ToneMapping::EXTENDED_REINHARD {
+				white_point: *white_point,
+			};
+			assert_eq!(invalid.map(grey(1.0)), ToneMapping::REINHARD.map(grey(1.0)));
+		}
+		assert!(
+			(ToneMapping::UNCHARTED2
+				.map(grey(UNCHARTED2_WHITE / 2.0))
+				.x() - 1.0)
+				.abs() < 1e-12
+		);
+		assert_eq!(ToneMapping::LINEAR.map(grey(3.0)), grey(3.0));
+	}
+
+	#[test]
+	fn test_reinhard_keeps_hue() {
+		let mapped = ToneMapping::REINHARD.map(Color::from(4.0, 2.0, 1.0));
+		assert!((mapped.x() / mapped.y() - 2.0).abs() < 1e-12);
+		assert!((mapped.y() / mapped.z() - 2.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn test_exposure_in_stops() {
+		let display = DisplaySettings {
+			tone_mapping: ToneMapping::LINEAR,
+			exposure: -2.0,
+		};
+		assert_eq!(display.apply(grey(2.0)), grey(0.5));
+	}
+}
@@ -1,6 +1,8 @@
 pub use progress::ProgressBar;
+pub use welford::Welford;
 
 pub mod progress;
+pub mod welford;
 
 pub fn calc_height(width: i32, aspect_ratio: f64) -> i32 {
 	(width as f64 / aspect_ratio) as i32
@@ -1,35 +1,32 @@
 use std::{
-	cell::RefCell,
 	io::{self, Write},
+	sync::atomic::{AtomicI32, Ordering},
 };
 
 pub struct ProgressBar {
-	current: RefCell<i32>,
+	current: AtomicI32,
 	total: i32,
 }
 
 impl ProgressBar {
 	pub fn from(total: i32) -> Self {
 		Self {
-			current: RefCell::new(0),
+			current: AtomicI32::new(0),
 			total,
 		}
 	}
 
-	fn calc(&self) -> f64 {
-		(*self.current.borrow() as f64 / self.total as f64) * 100.0
+	fn calc(&self, current: i32) -> f64 {
+		(current as f64 / self.total as f64) * 100.0
 	}
 
-	fn increment(&self) {
-		let curr = self.current.as_ptr();
-		unsafe {
-			*curr = *curr + 1;
-		}
+	fn increment(&self) -> i32 {
+		self.current.fetch_add(1, Ordering::Relaxed) + 1
 	}
 
 	pub fn inc(&self) {
-		self.increment();
-		eprint!("\rProgressBar: {:.2}%", self.calc());
+		let current = self.increment();
+		eprint!("\rProgressBar: {:.2}%", self.calc(current));
 		io::stderr().flush().unwrap();
 	}
 }
@@ -38,14 +35,30 @@ impl ProgressBar {
 fn test_increment() {
 	let p = ProgressBar::from(10);
 	p.increment();
-	assert_eq!(*p.current.borrow(), 1);
+	assert_eq!(p.current.load(Ordering::Relaxed), 1);
 }
 
 #[test]
 fn test_calc() {
 	let p = ProgressBar::from(10);
 	p.increment();
-	p.increment();
+	let current = p.increment();
+
+	assert_eq!(p.calc(current), 20.0);
+}
+
+#[test]
+fn test_increment_across_threads() {
+	let p = ProgressBar::from(400);
+	std::thread::scope(|s| {
+		for _ in 0..4 {
+			s.spawn(|| {
+				for _ in 0..100 {
+					p.increment();
+				}
+			});
+		}
+	});
 
-	assert_eq!(p.calc(), 20.0);
+	assert_eq!(p.current.load(Ordering::Relaxed), 400);
 }
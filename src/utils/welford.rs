@@ -0,0 +1,81 @@
+/// Welford's running mean and variance, numerically stable for long sequences
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Welford {
+	count: u32,
+	mean: f64,
+	m2: f64,
+}
+
+impl Welford {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// restores the state read from `count`, `mean` and `m2` of another accumulator
+	pub fn from(count: u32, mean: f64, m2: f64) -> Self {
+		Self { count, mean, m2 }
+	}
+
+	pub fn add(&mut self, value: f64) {
+		self.count += 1;
+		let delta = value - self.mean;
+		self.mean += delta / self.count as f64;
+		self.m2 += delta * (value - self.mean);
+	}
+
+	pub fn count(&self) -> u32 {
+		self.count
+	}
+
+	pub fn mean(&self) -> f64 {
+		self.mean
+	}
+
+	/// sum of the squared differences from the mean
+	pub fn m2(&self) -> f64 {
+		self.m2
+	}
+
+	/// unbiased sample variance, zero for fewer than two values
+	pub fn variance(&self) -> f64 {
+		if self.count < 2 {
+			return 0.0;
+		}
+		self.m2 / (self.count - 1) as f64
+	}
+
+	/// estimated standard deviation of the mean, infinite for fewer than two values
+	pub fn standard_error(&self) -> f64 {
+		if self.count < 2 {
+			return f64::INFINITY;
+		}
+		(self.variance() / self.count as f64).sqrt()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_mean_and_variance() {
+		let mut w = Welford::new();
+		assert_eq!(w.standard_error(), f64::INFINITY);
+		for v in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+			w.add(v);
+		}
+		assert_eq!(w.count(), 8);
+		assert_eq!(w.mean(), 5.0);
+		assert!((w.variance() - 32.0 / 7.0).abs() < 1e-12);
+		assert!((w.standard_error() - (32.0 / 56.0f64).sqrt()).abs() < 1e-12);
+	}
+
+	#[test]
+	fn test_large_offset_is_stable() {
+		let mut w = Welford::new();
+		for v in [1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0] {
+			w.add(v);
+		}
+		assert_eq!(w.variance(), 30.0);
+	}
+}
@@ -0,0 +1,207 @@
+use exr::prelude::{
+	f16, AnyChannel, AnyChannels, AttributeValue, Blocks, Compression, Encoding, FlatSamples,
+	Image, ImageAttributes, IntegerBounds, Layer, LayerAttributes, LineOrder, SmallVec, Text, Vec2,
+	WritableImage,
+};
+
+use std::collections::HashMap;
+
+use super::WriteResult;
+use crate::{Color, RenderingResult};
+
+/// writes the linear framebuffer and every auxiliary layer into one OpenEXR file,
+/// each layer becomes a named part with R, G and B channels
+pub struct EXRWriter {
+	path: String,
+	sample_type: EXRSampleType,
+	compression: EXRCompression,
+}
+
+#[derive(Clone, Copy)]
+pub enum EXRSampleType {
+	/// 16 bit floats
+	HALF,
+	/// 32 bit floats
+	FLOAT,
+}
+
+/// lossless compression methods
+#[derive(Clone, Copy)]
+pub enum EXRCompression {
+	NONE,
+	ZIP,
+	PIZ,
+}
+
+impl EXRWriter {
+	pub fn new(path: &str) -> Self {
+		EXRWriter::from(path, EXRSampleType::HALF, EXRCompression::ZIP)
+	}
+
+	pub fn from(path: &str, sample_type: EXRSampleType, compression: EXRCompression) -> Self {
+		Self {
+			path: path.to_string(),
+			sample_type,
+			compression,
+		}
+	}
+
+	fn layer(
+		&self,
+		name: &str,
+		size: Vec2<usize>,
+		colors: &[Color],
+		metadata: &HashMap<Text, AttributeValue>,
+	) -> Layer<AnyChannels<FlatSamples>> {
+		let channel = |name: &str, value: fn(&Color) -> f64| {
+			let samples = match self.sample_type {
+				EXRSampleType::HALF => {
+					FlatSamples::F16(colors.iter().map(|c| f16::from_f64(value(c))).collect())
+				}
+				EXRSampleType::FLOAT => {
+					FlatSamples::F32(colors.iter().map(|c| value(c) as f32).collect())
+				}
+			};
+			AnyChannel::new(name, samples)
+		};
+		let channels = AnyChannels::sort(SmallVec::from_vec(vec![
+			channel("R", Color::x),
+			channel("G", Color::y),
+			channel("B", Color::z),
+		]));
+		let encoding = Encoding {
+			compression: match self.compression {
+				EXRCompression::NONE => Compression::Uncompressed,
+				EXRCompression::ZIP => Compression::ZIP16,
+				EXRCompression::PIZ => Compression::PIZ,
+			},
+			blocks: Blocks::ScanLines,
+			line_order: LineOrder::Increasing,
+		};
+
+		let mut attributes = LayerAttributes::named(name);
+		attributes.other = metadata.clone();
+
+		Layer::new(size, attributes, encoding, channels)
+	}
+}
+
+impl WriteResult for EXRWriter {
+	fn write(
+		&self,
+		RenderingResult {
+			width,
+			height,
+			colors,
+			layers,
+			metadata,
+			..
+		}: RenderingResult,
+	) -> Result<(), Box<dyn std::error::Error>> {
+		// readers attach text attributes to the part they are stored in
+		let mut attributes = HashMap::new();
+		for (key, value) in metadata.iter() {
+			let text = |s: &str| {
+				Text::new_or_none(s).ok_or_else(|| format!("metadata '{}' is not latin-1 text", s))
+			};
+			attributes.insert(text(key)?, AttributeValue::Text(text(value)?));
+		}
+
+		let size = Vec2(width as usize, height as usize);
+		let mut parts = vec![self.layer("beauty", size, &colors, &attributes)];
+		for layer in layers.iter() {
+			parts.push(self.layer(&layer.name, size, &layer.colors, &attributes));
+		}
+
+		Image::from_layers(
+			ImageAttributes::new(IntegerBounds::from_dimensions(size)),
+			parts,
+		)
+		.write()
+		.to_file(&self.path)?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Layer as RenderLayer;
+	use exr::prelude::{read_all_flat_layers_from_file, FlatImage};
+
+	fn result() -> RenderingResult {
+		RenderingResult {
+			width: 2,
+			height: 1,
+			colors: vec![Color::from(16.5, 0.25, 0.0), Color::from(0.0, 1.0, 2.0)],
+			layers: vec![RenderLayer {
+				name: "normal".to_string(),
+				colors: vec![Color::from(0.0, 0.0, 1.0), Color::from(-1.0, 0.0, 0.0)],
+			}],
+			display: Default::default(),
+			metadata: vec![("Render settings".to_string(), "spp=4".to_string())],
+		}
+	}
+
+	fn write_and_read(name: &str, writer: impl Fn(&str) -> EXRWriter) -> FlatImage {
+		let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+		let path = path.to_string_lossy();
+		writer(&path).write(result()).expect("Failed writing file");
+		let image = read_all_flat_layers_from_file(&*path).expect("Failed reading file");
+		std::fs::remove_file(&*path).expect("File could not be deleted");
+		image
+	}
+
+	fn channel(layer: &Layer<AnyChannels<FlatSamples>>, name: &str) -> Vec<f32> {
+		layer
+			.channel_data
+			.list
+			.iter()
+			.find(|c| c.name == *name)
+			.map(|c| c.sample_data.values_as_f32().collect())
+			.expect("missing channel")
+	}
+
+	#[test]
+	fn test_float_layers() {
+		let image = write_and_read("raytracing_weekend_float.exr", |p| {
+			EXRWriter::from(p, EXRSampleType::FLOAT, EXRCompression::NONE)
+		});
+
+		let names: Vec<String> = image
+			.layer_data
+			.iter()
+			.map(|l| l.attributes.layer_name.as_ref().unwrap().to_string())
+			.collect();
+		assert_eq!(names, vec!["beauty", "normal"]);
+		assert_eq!(channel(&image.layer_data[0], "R"), vec![16.5, 0.0]);
+		assert_eq!(channel(&image.layer_data[0], "B"), vec![0.0, 2.0]);
+		assert_eq!(channel(&image.layer_data[1], "R"), vec![0.0, -1.0]);
+		for layer in image.layer_data.iter() {
+			assert!(matches!(
+				layer.attributes.other.get(&Text::from("Render settings")),
+				Some(AttributeValue::Text(t)) if t == "spp=4"
+			));
+		}
+	}
+
+	#[test]
+	fn test_half_compressed() {
+		for (name, compression) in [
+			("raytracing_weekend_zip.exr", EXRCompression::ZIP),
+			("raytracing_weekend_piz.exr", EXRCompression::PIZ),
+		] {
+			let image = write_and_read(name, |p| {
+				EXRWriter::from(p, EXRSampleType::HALF, compression)
+			});
+			let beauty = &image.layer_data[0];
+			assert!(matches!(
+				beauty.channel_data.list[0].sample_data,
+				FlatSamples::F16(_)
+			));
+			assert_eq!(channel(beauty, "R"), vec![16.5, 0.0]);
+			assert_eq!(channel(beauty, "G"), vec![0.25, 1.0]);
+		}
+	}
+}
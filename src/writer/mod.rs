@@ -1,8 +1,62 @@
+use std::error::Error;
+use std::path::Path;
+
+pub use self::exr::{EXRCompression, EXRSampleType, EXRWriter};
 use crate::RenderingResult;
-pub use ppm::PPMWriter;
+pub use pfm::PFMWriter;
+pub use png::{PNGColorType, PNGWriter};
+pub use ppm::{MagicNumber, PPMWriter};
 
+mod exr;
+mod pfm;
+mod png;
 mod ppm;
 
 pub trait WriteResult {
 	fn write(&self, result: RenderingResult) -> Result<(), Box<dyn std::error::Error>>;
 }
+
+/// picks the writer matching the file extension of `path` with its default options
+pub fn writer_for_path(path: &str) -> Result<Box<dyn WriteResult>, Box<dyn Error>> {
+	let extension = Path::new(path)
+		.extension()
+		.and_then(|e| e.to_str())
+		.ok_or_else(|| {
+			format!(
+				"cannot pick an output format for '{}' without an extension",
+				path
+			)
+		})?;
+
+	writer_for_format(extension, path)
+}
+
+/// picks the writer for a format name like `png` with its default options
+pub fn writer_for_format(format: &str, path: &str) -> Result<Box<dyn WriteResult>, Box<dyn Error>> {
+	match format.to_ascii_lowercase().as_str() {
+		"ppm" => Ok(Box::new(PPMWriter::new(path))),
+		"png" => Ok(Box::new(PNGWriter::new(path))),
+		"pfm" => Ok(Box::new(PFMWriter::new(path))),
+		"exr" => Ok(Box::new(EXRWriter::new(path))),
+		other => Err(format!("unsupported output format '{}'", other).into()),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_writer_for_path() {
+		assert!(writer_for_path("out.ppm").is_ok());
+		assert!(writer_for_path("./assets/OUT.PNG").is_ok());
+		assert!(writer_for_path("out.pfm").is_ok());
+		assert!(writer_for_path("out.exr").is_ok());
+		assert_eq!(
+			writer_for_path("out.bmp").err().unwrap().to_string(),
+			"unsupported output format 'bmp'"
+		);
+		assert!(writer_for_path("out").is_err());
+		assert!(writer_for_format("EXR", "out").is_ok());
+	}
+}
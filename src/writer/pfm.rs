@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use super::WriteResult;
+use crate::RenderingResult;
+
+/// writes the linear framebuffer as a little endian Portable Float Map
+pub struct PFMWriter {
+	path: String,
+}
+
+impl PFMWriter {
+	pub fn new(path: &str) -> Self {
+		Self {
+			path: path.to_string(),
+		}
+	}
+}
+
+impl WriteResult for PFMWriter {
+	fn write(
+		&self,
+		RenderingResult {
+			width,
+			height,
+			colors,
+			..
+		}: RenderingResult,
+	) -> Result<(), Box<dyn std::error::Error>> {
+		let mut out = BufWriter::new(File::create(&self.path)?);
+		// a negative scale marks little endian samples
+		write!(out, "PF\n{} {}\n-1.0\n", width, height)?;
+		// rows are stored from the bottom of the image to the top
+		for row in colors.chunks(width as usize).rev() {
+			for c in row.iter() {
+				for channel in [c.x(), c.y(), c.z()] {
+					out.write_all(&(channel as f32).to_le_bytes())?;
+				}
+			}
+		}
+
+		out.flush()?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Color;
+
+	#[test]
+	fn test_write() {
+		let path =
+			std::env::temp_dir().join(format!("raytracing_weekend_{}.pfm", std::process::id()));
+		let path = path.to_string_lossy();
+		let result = RenderingResult {
+			width: 1,
+			height: 2,
+			colors: vec![Color::from(4.5, 0.25, -1.0), Color::from(0.0, 1.0, 100.0)],
+			layers: vec![],
+			display: Default::default(),
+			metadata: vec![],
+		};
+		PFMWriter::new(&path)
+			.write(result)
+			.expect("Failed writing file");
+		let bytes = std::fs::read(&*path).expect("Failed reading file");
+		std::fs::remove_file(&*path).expect("File could not be deleted");
+
+		let header = b"PF\n1 2\n-1.0\n";
+		assert_eq!(&bytes[..header.len()], header);
+		let samples: Vec<f32> = bytes[header.len()..]
+			.chunks(4)
+			.map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+			.collect();
+		assert_eq!(samples, vec![0.0, 1.0, 100.0, 4.5, 0.25, -1.0]);
+	}
+}
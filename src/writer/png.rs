@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use super::WriteResult;
+use crate::pixel::Pixel;
+use crate::{Color, DisplaySettings, RenderingResult};
+
+pub struct PNGWriter {
+	path: String,
+	bit_depth: u8,
+	color_type: PNGColorType,
+}
+
+/// channels written per pixel, the alpha channel is always opaque
+#[derive(Clone, Copy)]
+pub enum PNGColorType {
+	RGB,
+	RGBA,
+}
+
+impl PNGWriter {
+	pub fn new(path: &str) -> Self {
+		PNGWriter::from(path, 8, PNGColorType::RGB)
+	}
+
+	/// `bit_depth` is either 8 or 16 bits per channel
+	pub fn from(path: &str, bit_depth: u8, color_type: PNGColorType) -> Self {
+		assert!(
+			bit_depth == 8 || bit_depth == 16,
+			"bit_depth must be 8 or 16"
+		);
+		Self {
+			path: path.to_string(),
+			bit_depth,
+			color_type,
+		}
+	}
+
+	fn encode(&self, colors: &[Color], display: &DisplaySettings) -> Vec<u8> {
+		let max_color = if self.bit_depth == 8 { 255 } else { 65535 };
+		let channels = match self.color_type {
+			PNGColorType::RGB => 3,
+			PNGColorType::RGBA => 4,
+		};
+		let mut data = Vec::with_capacity(colors.len() * channels * (self.bit_depth as usize / 8));
+		for c in colors.iter() {
+			let p = Pixel::from_color_with_max(display.apply(*c), max_color);
+			let alpha = [max_color];
+			let values = [p.r(), p.g(), p.b()];
+			let values = values.iter().chain(alpha.iter()).take(channels);
+			for &v in values {
+				if self.bit_depth == 8 {
+					data.push(v as u8);
+				} else {
+					// PNG stores samples in network byte order
+					data.extend_from_slice(&(v as u16).to_be_bytes());
+				}
+			}
+		}
+		data
+	}
+}
+
+impl WriteResult for PNGWriter {
+	fn write(
+		&self,
+		RenderingResult {
+			width,
+			height,
+			colors,
+			display,
+			metadata,
+			..
+		}: RenderingResult,
+	) -> Result<(), Box<dyn std::error::Error>> {
+		let file = BufWriter::new(File::create(&self.path)?);
+		let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+		encoder.set_color(match self.color_type {
+			PNGColorType::RGB => png::ColorType::Rgb,
+			PNGColorType::RGBA => png::ColorType::Rgba,
+		});
+		encoder.set_depth(match self.bit_depth {
+			8 => png::BitDepth::Eight,
+			_ => png::BitDepth::Sixteen,
+		});
+		for (key, value) in metadata.into_iter() {
+			encoder.add_text_chunk(key, value)?;
+		}
+
+		let mut writer = encoder.write_header()?;
+		writer.write_image_data(&self.encode(&colors, &display))?;
+		writer.finish()?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn result() -> RenderingResult {
+		let colors = vec![Color::from(0.0, 0.25, 1.0), Color::from(1.0, 0.0, 0.0)];
+		RenderingResult {
+			width: 2,
+			height: 1,
+			colors,
+			layers: vec![],
+			display: Default::default(),
+			metadata: vec![("Render settings".to_string(), "spp=4".to_string())],
+		}
+	}
+
+	fn write_and_decode(writer: PNGWriter, path: &str) -> (png::OutputInfo, Vec<u8>, Vec<String>) {
+		writer.write(result()).expect("Failed writing file");
+		let decoder = png::Decoder::new(File::open(path).unwrap());
+		let mut reader = decoder.read_info().unwrap();
+		let mut buf = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut buf).unwrap();
+		buf.truncate(info.buffer_size());
+		let texts = reader
+			.info()
+			.uncompressed_latin1_text
+			.iter()
+			.map(|t| format!("{}={}", t.keyword, t.text))
+			.collect();
+		std::fs::remove_file(path).expect("File could not be deleted");
+		(info, buf, texts)
+	}
+
+	#[test]
+	fn test_eight_bit_rgb() {
+		let path =
+			std::env::temp_dir().join(format!("raytracing_weekend_8_{}.png", std::process::id()));
+		let path = path.to_string_lossy();
+		let (info, data, texts) = write_and_decode(PNGWriter::new(&path), &path);
+
+		assert_eq!((info.width, info.height), (2, 1));
+		assert_eq!(data, vec![0, 137, 255, 255, 0, 0]);
+		assert_eq!(texts, vec!["Render settings=spp=4".to_string()]);
+	}
+
+	#[test]
+	fn test_sixteen_bit_rgba() {
+		let path =
+			std::env::temp_dir().join(format!("raytracing_weekend_16_{}.png", std::process::id()));
+		let path = path.to_string_lossy();
+		let writer = PNGWriter::from(&path, 16, PNGColorType::RGBA);
+		let (info, data, _) = write_and_decode(writer, &path);
+
+		assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+		assert_eq!(info.color_type, png::ColorType::Rgba);
+		assert_eq!(&data[0..8], &[0, 0, 0x89, 0x7f, 0xff, 0xff, 0xff, 0xff]);
+	}
+}
@@ -1,9 +1,9 @@
 use core::fmt;
-use std::fmt::Write;
-use std::io::Write as IoWrite;
-use std::{fmt::Display, fs::File};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
 use super::WriteResult;
+use crate::pixel::Pixel;
 use crate::RenderingResult;
 
 pub struct PPMWriter {
@@ -12,16 +12,28 @@ pub struct PPMWriter {
 	max_color: i32,
 }
 
-enum MagicNumber {
+/// ASCII (P3) or binary (P6) pixel data
+#[derive(Clone, Copy)]
+pub enum MagicNumber {
 	P3,
+	P6,
 }
 
 impl PPMWriter {
 	pub fn new(path: &str) -> Self {
+		PPMWriter::from(path, MagicNumber::P3, 255)
+	}
+
+	/// `max_color` above 255 stores two bytes per channel in binary files
+	pub fn from(path: &str, magic_number: MagicNumber, max_color: i32) -> Self {
+		assert!(
+			(1..=65535).contains(&max_color),
+			"max_color must be within 1 and 65535"
+		);
 		Self {
 			path: path.to_string(),
-			magic_number: MagicNumber::P3,
-			max_color: 255,
+			magic_number,
+			max_color,
 		}
 	}
 }
@@ -32,31 +44,101 @@ impl WriteResult for PPMWriter {
 		RenderingResult {
 			width,
 			height,
-			pixels,
+			colors,
+			display,
+			..
 		}: RenderingResult,
 	) -> Result<(), Box<dyn std::error::Error>> {
-		let mut file = File::create(&self.path)?;
-		let mut out = format!(
+		let mut out = BufWriter::new(File::create(&self.path)?);
+		write!(
+			out,
 			"{}\n{} {}\n{}\n",
-			self.magic_number.to_string(),
-			width,
-			height,
-			self.max_color,
-		);
-		for p in pixels.iter() {
-			writeln!(out, "{} {} {}", p.r(), p.g(), p.b())?;
+			self.magic_number, width, height, self.max_color,
+		)?;
+		for c in colors.iter() {
+			let p = Pixel::from_color_with_max(display.apply(*c), self.max_color);
+			match self.magic_number {
+				MagicNumber::P3 => writeln!(out, "{} {} {}", p.r(), p.g(), p.b())?,
+				MagicNumber::P6 => {
+					for channel in [p.r(), p.g(), p.b()] {
+						if self.max_color < 256 {
+							out.write_all(&[channel as u8])?;
+						} else {
+							out.write_all(&(channel as u16).to_be_bytes())?;
+						}
+					}
+				}
+			}
 		}
 
-		file.write_all(out.as_bytes())?;
+		out.flush()?;
 
 		Ok(())
 	}
 }
 
-impl Display for MagicNumber {
+impl fmt::Display for MagicNumber {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
-			&MagicNumber::P3 => write!(f, "P3"),
+			MagicNumber::P3 => write!(f, "P3"),
+			MagicNumber::P6 => write!(f, "P6"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::loader::load_ppm;
+	use crate::Color;
+
+	fn result() -> RenderingResult {
+		let colors = vec![
+			Color::from(0.0, 0.25, 1.0),
+			Color::from(1.0, 0.0, 0.0),
+			Color::from(0.01, 0.5, 0.75),
+			Color::from(4.0, -1.0, 0.0),
+		];
+		RenderingResult {
+			width: 2,
+			height: 2,
+			colors,
+			layers: vec![],
+			display: Default::default(),
+			metadata: vec![],
+		}
+	}
+
+	fn write_and_load(name: &str, writer: impl Fn(&str) -> PPMWriter) -> Vec<[u32; 3]> {
+		let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+		let path = path.to_string_lossy();
+		writer(&path).write(result()).expect("Failed writing file");
+		let img = load_ppm(&path).expect("Failed reading file");
+		std::fs::remove_file(&*path).expect("File could not be deleted");
+
+		assert_eq!((img.width, img.height), (2, 2));
+		img.data
+	}
+
+	#[test]
+	fn test_binary_matches_ascii() {
+		let ascii = write_and_load("raytracing_weekend_p3.ppm", PPMWriter::new);
+		let binary = write_and_load("raytracing_weekend_p6.ppm", |p| {
+			PPMWriter::from(p, MagicNumber::P6, 255)
+		});
+
+		assert_eq!(ascii, binary);
+		assert_eq!(ascii[0], [0, 137, 255]);
+	}
+
+	#[test]
+	fn test_sixteen_bit() {
+		for magic_number in [MagicNumber::P3, MagicNumber::P6] {
+			let data = write_and_load("raytracing_weekend_16.ppm", |p| {
+				PPMWriter::from(p, magic_number, 65535)
+			});
+			assert_eq!(data[0], [0, 35199, 65535]);
+			assert_eq!(data[3], [65535, 0, 0]);
 		}
 	}
 }